@@ -0,0 +1,566 @@
+//! Deadzones and response curves for analog sticks, treating the two axes
+//! of a stick as one vector, and pairing the two axes into one event per
+//! frame.
+
+use std::io;
+
+use crate::pipeline::Stage;
+use crate::{AbsInfo, Axis, Device, Event};
+
+/// The two-axis sticks that can be processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Stick {
+    /// `JoyX` + `JoyY`
+    Joy,
+    /// `CamX` + `CamY`
+    Cam,
+    /// `ScrollX` + `ScrollY`
+    Scroll,
+    /// `ActionWheelX` + `ActionWheelY`
+    ActionWheel,
+}
+
+impl Stick {
+    pub const ALL: [Stick; 4] = [Stick::Joy, Stick::Cam, Stick::Scroll, Stick::ActionWheel];
+
+    /// The X and Y axes of this stick.
+    pub fn axes(self) -> [Axis; 2] {
+        match self {
+            Stick::Joy => [Axis::JoyX, Axis::JoyY],
+            Stick::Cam => [Axis::CamX, Axis::CamY],
+            Stick::Scroll => [Axis::ScrollX, Axis::ScrollY],
+            Stick::ActionWheel => [Axis::ActionWheelX, Axis::ActionWheelY],
+        }
+    }
+
+    /// The stick an axis event belongs to, with `false` for X and `true`
+    /// for Y, and its raw value.
+    pub fn of<T>(event: &Event<T>) -> Option<(Stick, bool, &T)> where T: TryFrom<i32> {
+        let Event::Axis(axis, value) = event else {
+            return None;
+        };
+        Stick::ALL.into_iter().find_map(|stick| {
+            let index = stick.axes().iter().position(|a| a == axis)?;
+            Some((stick, index == 1, value))
+        })
+    }
+
+    /// Build the X or Y axis event of this stick.
+    pub fn event<T>(self, y: bool, value: T) -> Event<T> where T: TryFrom<i32> {
+        Event::Axis(self.axes()[y as usize], value)
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Shape of the inner deadzone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Deadzone {
+    /// Each axis is zeroed on its own below the threshold and rescaled above
+    /// it.  Keeps straight lines easy to hold, but snaps diagonals.
+    Axial,
+    /// The stick is zeroed while its distance from center is below the
+    /// threshold, and passed through unchanged otherwise.
+    Radial,
+    /// Like `Radial`, but the remaining range is rescaled so output starts
+    /// from zero at the deadzone edge.
+    ScaledRadial,
+}
+
+/// Maps the deflection (`0.0..=1.0`) of a stick to its output.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResponseCurve {
+    Linear,
+    /// `deflection.powf(exponent)`, values above `1.0` give finer control
+    /// near center.
+    Exponential(f32),
+    /// Linear interpolation between `(input, output)` points sorted by
+    /// input.  `(0, 0)` and `(1, 1)` are implied if not given.
+    Piecewise(Vec<(f32, f32)>),
+}
+
+impl ResponseCurve {
+    pub fn apply(&self, x: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => x,
+            ResponseCurve::Exponential(exponent) => x.powf(*exponent),
+            ResponseCurve::Piecewise(points) => {
+                let mut prev = (0.0, 0.0);
+                for &(px, py) in points.iter().chain(std::iter::once(&(1.0, 1.0))) {
+                    if x <= px {
+                        let span = px - prev.0;
+                        if span <= f32::EPSILON {
+                            return py;
+                        }
+                        return prev.1 + (py - prev.1) * (x - prev.0) / span;
+                    }
+                    prev = (px, py);
+                }
+                prev.1
+            }
+        }
+    }
+}
+
+/// Processing settings of one stick.  Thresholds are fractions of the full
+/// deflection.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StickConfig {
+    /// Raw value range, usually from the device's absinfo.
+    pub minimum: i32,
+    pub maximum: i32,
+    pub deadzone: Deadzone,
+    /// Inner deadzone size.
+    pub inner: f32,
+    /// Deflections within this distance of the edge report full deflection.
+    pub outer: f32,
+    /// Smallest non-zero output deflection, to overcome a deadzone applied
+    /// further down the line (e.g. by a game).
+    pub anti_deadzone: f32,
+    pub curve: ResponseCurve,
+}
+
+impl StickConfig {
+    pub fn new(minimum: i32, maximum: i32) -> Self {
+        Self {
+            minimum,
+            maximum,
+            deadzone: Deadzone::ScaledRadial,
+            inner: 0.0,
+            outer: 0.0,
+            anti_deadzone: 0.0,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    pub fn from_absinfo(info: &AbsInfo) -> Self {
+        Self::new(info.minimum, info.maximum)
+    }
+
+    pub fn deadzone(mut self, deadzone: Deadzone, inner: f32) -> Self {
+        self.deadzone = deadzone;
+        self.inner = inner;
+        self
+    }
+
+    pub fn outer(mut self, outer: f32) -> Self {
+        self.outer = outer;
+        self
+    }
+
+    pub fn anti_deadzone(mut self, anti_deadzone: f32) -> Self {
+        self.anti_deadzone = anti_deadzone;
+        self
+    }
+
+    pub fn curve(mut self, curve: ResponseCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    fn center(&self) -> f32 {
+        (self.minimum as f32 + self.maximum as f32) / 2.0
+    }
+
+    fn half_range(&self) -> f32 {
+        ((self.maximum as f32 - self.minimum as f32) / 2.0).max(f32::EPSILON)
+    }
+
+    fn normalize(&self, raw: i32) -> f32 {
+        ((raw as f32 - self.center()) / self.half_range()).clamp(-1.0, 1.0)
+    }
+
+    fn denormalize(&self, value: f32) -> i32 {
+        (self.center() + value.clamp(-1.0, 1.0) * self.half_range()).round_ties_even() as i32
+    }
+
+    fn rescale(&self, deflection: f32) -> f32 {
+        let live = (1.0 - self.inner - self.outer).max(f32::EPSILON);
+        ((deflection - self.inner) / live).clamp(0.0, 1.0)
+    }
+
+    /// Process a raw `(x, y)` position into an output position in the same
+    /// raw range.
+    pub fn apply(&self, x: i32, y: i32) -> (i32, i32) {
+        let (mut x, mut y) = (self.normalize(x), self.normalize(y));
+        match self.deadzone {
+            Deadzone::Axial => {
+                let axis = |v: f32| v.signum() * if v.abs() < self.inner { 0.0 } else { self.rescale(v.abs()) };
+                (x, y) = (axis(x), axis(y));
+            }
+            Deadzone::Radial => {
+                let magnitude = x.hypot(y);
+                if magnitude < self.inner {
+                    (x, y) = (0.0, 0.0);
+                } else if magnitude > 1.0 - self.outer {
+                    (x, y) = (x / magnitude, y / magnitude);
+                }
+            }
+            Deadzone::ScaledRadial => {
+                let magnitude = x.hypot(y);
+                if magnitude < self.inner.max(f32::EPSILON) {
+                    (x, y) = (0.0, 0.0);
+                } else {
+                    let scale = self.rescale(magnitude) / magnitude;
+                    (x, y) = (x * scale, y * scale);
+                }
+            }
+        }
+
+        let magnitude = x.hypot(y);
+        if magnitude > f32::EPSILON {
+            let shaped = self.curve.apply(magnitude.min(1.0));
+            let shaped = self.anti_deadzone + (1.0 - self.anti_deadzone) * shaped;
+            let scale = shaped / magnitude.min(1.0);
+            (x, y) = (x * scale, y * scale);
+        } else {
+            (x, y) = (0.0, 0.0);
+        }
+        (self.denormalize(x), self.denormalize(y))
+    }
+}
+
+/// Applies [`StickConfig`]s to the axis events of the configured sticks.
+///
+/// Whenever either axis of a stick moves, both axes are re-processed and
+/// each axis whose output changed is emitted.  Events of other sticks and
+/// axes pass through untouched.
+#[derive(Debug, Clone, Default)]
+pub struct StickProcessor {
+    sticks: [Option<StickState>; 4],
+}
+
+#[derive(Debug, Clone)]
+struct StickState {
+    config: StickConfig,
+    raw: (i32, i32),
+    output: Option<(i32, i32)>,
+}
+
+impl StickProcessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, stick: Stick, config: StickConfig) {
+        let raw = (config.denormalize(0.0), config.denormalize(0.0));
+        self.sticks[stick.index()] = Some(StickState { config, raw, output: None });
+    }
+
+    pub fn with(mut self, stick: Stick, config: StickConfig) -> Self {
+        self.set(stick, config);
+        self
+    }
+
+    pub fn config(&self, stick: Stick) -> Option<&StickConfig> {
+        self.sticks[stick.index()].as_ref().map(|state| &state.config)
+    }
+}
+
+impl<T> Stage<T> for StickProcessor where T: TryFrom<i32> + Copy + Into<f64> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let Some((stick, is_y, value)) = Stick::of(&event) else {
+            out.push(event);
+            return;
+        };
+        let Some(state) = &mut self.sticks[stick.index()] else {
+            out.push(event);
+            return;
+        };
+        let raw = (*value).into().round() as i32;
+        if is_y {
+            state.raw.1 = raw;
+        } else {
+            state.raw.0 = raw;
+        }
+        let (x, y) = state.config.apply(state.raw.0, state.raw.1);
+        let previous = state.output.replace((x, y));
+        for (axis_is_y, value, old) in [(false, x, previous.map(|p| p.0)), (true, y, previous.map(|p| p.1))] {
+            // The moved axis is always reported, the other one only if the
+            // shared processing changed it.
+            if axis_is_y == is_y || old.is_some_and(|old| old != value) {
+                if let Ok(value) = T::try_from(value) {
+                    out.push(stick.event(axis_is_y, value));
+                }
+            }
+        }
+    }
+}
+
+/// Both axes of a stick at the end of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StickPosition<T = i32> {
+    pub stick: Stick,
+    pub x: T,
+    pub y: T,
+}
+
+impl<T> StickPosition<T> where T: Copy + Into<f64> {
+    /// Distance from `(0, 0)`.  Meaningful for values centered on zero,
+    /// e.g. after a [`StickProcessor`] or from joydev.
+    pub fn magnitude(&self) -> f64 {
+        self.x.into().hypot(self.y.into())
+    }
+
+    /// Angle in radians from the right, counter-clockwise with up positive
+    /// (evdev Y grows downwards), in `-PI..=PI` with straight left at `PI`.
+    pub fn angle(&self) -> f64 {
+        (0.0 - self.y.into()).atan2(self.x.into())
+    }
+}
+
+/// What a [`StickPairer`] emits: the events it was fed, with the axis events
+/// of sticks replaced by one position per stick and frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StickOutput<T = i32> where T: TryFrom<i32> {
+    Event(Event<T>),
+    Stick(StickPosition<T>),
+}
+
+/// Pairs the X and Y axis events of each [`Stick`] into a
+/// [`StickPosition`] per frame, so a diagonal move doesn't arrive as two
+/// steps.
+///
+/// Feed it the events of one frame, e.g. from [`Device::next_frame`], and
+/// call [`StickPairer::sync`] at its end.  Axes that haven't been reported
+/// yet read as zero unless seeded with [`StickPairer::for_device`] or
+/// [`StickPairer::set`].
+#[derive(Debug, Clone)]
+pub struct StickPairer<T = i32> {
+    positions: [(T, T); 4],
+    moved: [bool; 4],
+}
+
+impl<T> StickPairer<T> where T: TryFrom<i32> + Copy {
+    pub fn new() -> Self {
+        let zero = T::try_from(0).ok().expect("axis value type can't represent 0");
+        Self { positions: [(zero, zero); 4], moved: [false; 4] }
+    }
+
+    /// Start from the current positions of the sticks of a device.
+    pub fn for_device(device: &Device) -> io::Result<Self> {
+        let mut pairer = Self::new();
+        for stick in Stick::ALL {
+            let code = |y: bool| match stick.axes()[y as usize].kind().codes() {
+                &[(type_, code)] if type_ == input_linux_sys::EV_ABS as u16 => Some(code),
+                _ => None,
+            };
+            let (Some(x), Some(y)) = (code(false), code(true)) else {
+                continue;
+            };
+            if device.has(input_linux_sys::EV_ABS as _, x)? && device.has(input_linux_sys::EV_ABS as _, y)? {
+                let value = |code| -> io::Result<Option<T>> { Ok(T::try_from(device.absinfo(code)?.value).ok()) };
+                if let (Some(x), Some(y)) = (value(x)?, value(y)?) {
+                    pairer.set(stick, x, y);
+                }
+            }
+        }
+        Ok(pairer)
+    }
+
+    /// Current position of a stick.
+    pub fn position(&self, stick: Stick) -> StickPosition<T> {
+        let (x, y) = self.positions[stick.index()];
+        StickPosition { stick, x, y }
+    }
+
+    /// Set the position of a stick without reporting it.
+    pub fn set(&mut self, stick: Stick, x: T, y: T) {
+        self.positions[stick.index()] = (x, y);
+    }
+
+    /// Handle one event of the current frame.  Stick axis events are held
+    /// until [`StickPairer::sync`], other events come straight back.
+    pub fn feed(&mut self, event: Event<T>) -> Option<Event<T>> {
+        let Some((stick, is_y, value)) = Stick::of(&event) else {
+            return Some(event);
+        };
+        let position = &mut self.positions[stick.index()];
+        if is_y {
+            position.1 = *value;
+        } else {
+            position.0 = *value;
+        }
+        self.moved[stick.index()] = true;
+        None
+    }
+
+    /// End the current frame, returning the position of each stick that
+    /// moved in it.
+    pub fn sync(&mut self) -> Vec<StickPosition<T>> {
+        let mut out = Vec::new();
+        for stick in Stick::ALL {
+            if std::mem::take(&mut self.moved[stick.index()]) {
+                out.push(self.position(stick));
+            }
+        }
+        out
+    }
+
+    /// Pair the sticks of a whole frame.  The other events come first, in
+    /// order, followed by the stick positions.
+    pub fn frame(&mut self, events: impl IntoIterator<Item = Event<T>>) -> Vec<StickOutput<T>> {
+        let mut out: Vec<_> = events.into_iter().filter_map(|event| self.feed(event)).map(StickOutput::Event).collect();
+        out.extend(self.sync().into_iter().map(StickOutput::Stick));
+        out
+    }
+}
+
+impl<T> Default for StickPairer<T> where T: TryFrom<i32> + Copy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    use super::*;
+    use crate::Button;
+
+    fn config(deadzone: Deadzone, inner: f32) -> StickConfig {
+        StickConfig::new(-1000, 1000).deadzone(deadzone, inner)
+    }
+
+    #[test]
+    fn axial_deadzone() {
+        let config = config(Deadzone::Axial, 0.2);
+        assert_eq!(config.apply(100, 900), (0, 875));
+        assert_eq!(config.apply(-150, 150), (0, 0));
+        // Corners stay at full deflection on both axes.
+        assert_eq!(config.apply(-1000, 1000), (-1000, 1000));
+    }
+
+    #[test]
+    fn radial_deadzone() {
+        let config = config(Deadzone::Radial, 0.2);
+        assert_eq!(config.apply(100, 100), (0, 0));
+        assert_eq!(config.apply(300, 0), (300, 0));
+        assert_eq!(config.apply(0, -250), (0, -250));
+    }
+
+    #[test]
+    fn scaled_radial_deadzone() {
+        let config = config(Deadzone::ScaledRadial, 0.2);
+        assert_eq!(config.apply(100, 0), (0, 0));
+        assert_eq!(config.apply(600, 0), (500, 0));
+        // The direction is kept.
+        assert_eq!(config.apply(300, 400), (225, 300));
+        assert_eq!(config.apply(0, 0), (0, 0));
+    }
+
+    #[test]
+    fn outer_deadzone() {
+        let radial = config(Deadzone::Radial, 0.0).outer(0.1);
+        assert_eq!(radial.apply(950, 0), (1000, 0));
+        assert_eq!(radial.apply(700, 700), (707, 707));
+        assert_eq!(radial.apply(500, 0), (500, 0));
+        let scaled = config(Deadzone::ScaledRadial, 0.1).outer(0.1);
+        assert_eq!(scaled.apply(0, -950), (0, -1000));
+    }
+
+    #[test]
+    fn anti_deadzone() {
+        let config = config(Deadzone::ScaledRadial, 0.2).anti_deadzone(0.2);
+        assert_eq!(config.apply(100, 0), (0, 0));
+        assert_eq!(config.apply(201, 0), (201, 0));
+        assert_eq!(config.apply(600, 0), (600, 0));
+        assert_eq!(config.apply(1000, 0), (1000, 0));
+    }
+
+    #[test]
+    fn exponential_curve() {
+        let config = config(Deadzone::ScaledRadial, 0.0).curve(ResponseCurve::Exponential(2.0));
+        assert_eq!(config.apply(500, 0), (250, 0));
+        assert_eq!(config.apply(0, 1000), (0, 1000));
+    }
+
+    #[test]
+    fn piecewise_curve_endpoints() {
+        let curve = ResponseCurve::Piecewise(vec![(0.5, 0.2)]);
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(0.5), 0.2);
+        assert_eq!(curve.apply(0.75), 0.6);
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_eq!(curve.apply(1.5), 1.0);
+        // Explicit endpoints override the implied ones.
+        let curve = ResponseCurve::Piecewise(vec![(0.0, 0.1), (1.0, 0.9)]);
+        assert_eq!(curve.apply(0.0), 0.1);
+        assert_eq!(curve.apply(1.0), 0.9);
+    }
+
+    #[test]
+    fn piecewise_curve_monotonic() {
+        let curve = ResponseCurve::Piecewise(vec![(0.2, 0.0), (0.4, 0.1), (0.4, 0.3), (0.8, 0.9)]);
+        let outputs: Vec<f32> = (0..=100).map(|step| curve.apply(step as f32 / 100.0)).collect();
+        assert!(outputs.windows(2).all(|pair| pair[0] <= pair[1]), "{outputs:?}");
+        assert_eq!(outputs[100], 1.0);
+    }
+
+    #[test]
+    fn processor_reports_both_axes() {
+        let mut processor = StickProcessor::new().with(Stick::Joy, config(Deadzone::ScaledRadial, 0.2));
+        let mut out = Vec::new();
+        processor.process(Event::Axis(Axis::JoyX, 600), &mut out);
+        processor.process(Event::Axis(Axis::JoyY, 800), &mut out);
+        processor.process(Event::Button(Button::Trigger, true), &mut out);
+        processor.process(Event::Axis(Axis::CamX, 100), &mut out);
+        assert_eq!(out, [
+            Event::Axis(Axis::JoyX, 500),
+            // The radial scaling moves X as well.
+            Event::Axis(Axis::JoyX, 600),
+            Event::Axis(Axis::JoyY, 800),
+            Event::Button(Button::Trigger, true),
+            Event::Axis(Axis::CamX, 100),
+        ]);
+    }
+
+    fn angle(x: i32, y: i32) -> f64 {
+        StickPosition { stick: Stick::Joy, x, y }.angle()
+    }
+
+    fn magnitude(x: i32, y: i32) -> f64 {
+        StickPosition { stick: Stick::Joy, x, y }.magnitude()
+    }
+
+    #[test]
+    fn polar_at_extremes() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(angle(32767, 0), 0.0));
+        assert!(close(angle(0, -32768), FRAC_PI_2));
+        assert!(close(angle(-32768, 0), PI));
+        assert!(close(angle(0, 32767), -FRAC_PI_2));
+        assert!(close(angle(32767, -32767), FRAC_PI_4));
+        assert!(close(angle(0, 0), 0.0));
+        assert_eq!(magnitude(0, 0), 0.0);
+        assert_eq!(magnitude(-32768, 0), 32768.0);
+        assert_eq!(magnitude(0, 32767), 32767.0);
+        assert!(close(magnitude(-32768, -32768), 32768.0 * 2f64.sqrt()));
+    }
+
+    #[test]
+    fn pairer_reports_once_per_frame() {
+        let mut pairer = StickPairer::<i32>::new();
+        let out = pairer.frame([Event::Axis(Axis::JoyX, 10), Event::Button(Button::Trigger, true), Event::Axis(Axis::JoyY, -20), Event::Axis(Axis::JoyX, 30)]);
+        assert_eq!(out, [
+            StickOutput::Event(Event::Button(Button::Trigger, true)),
+            StickOutput::Stick(StickPosition { stick: Stick::Joy, x: 30, y: -20 }),
+        ]);
+        // Only the moved stick, keeping the other axis.
+        let out = pairer.frame([Event::Axis(Axis::JoyY, 5), Event::Axis(Axis::ScrollX, 1)]);
+        assert_eq!(out, [
+            StickOutput::Stick(StickPosition { stick: Stick::Joy, x: 30, y: 5 }),
+            StickOutput::Stick(StickPosition { stick: Stick::Scroll, x: 1, y: 0 }),
+        ]);
+        assert!(pairer.sync().is_empty());
+    }
+}
@@ -0,0 +1,399 @@
+//! Joystick calibration: measuring the real range of each axis, and
+//! applying it in software or pushing it to the kernel.
+//!
+//! Profiles are saved as text, one axis per line:
+//!
+//! ```text
+//! # linux-input-event calibration 1
+//! device serial:045e:028e:0:3032363030
+//! axis 00 -31000 12 30500 96 -32768 32767
+//! axis 02 0 - 1020 4 0 1023
+//! ```
+//!
+//! An `axis` line holds the `ABS_*` code in hex, the measured minimum,
+//! center (`-` for axes that rest at an end), maximum and flat, and the
+//! advertised range.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use input_linux_sys::*;
+
+use crate::pipeline::Stage;
+use crate::{AbsInfo, Device, Event, StableId};
+
+/// The steps of a [`Calibrator`], in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CalibrationStep {
+    /// The user leaves every axis at rest.
+    Center,
+    /// The user moves every axis to both of its extents.
+    Extents,
+    /// The user lets go again, confirming the rest position.
+    Release,
+    Done,
+}
+
+/// Measured range of one axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AxisCalibration {
+    pub minimum: i32,
+    /// Rest position of self-centering axes, `None` for axes that rest at
+    /// an end, like throttles and pedals.
+    pub center: Option<i32>,
+    pub maximum: i32,
+    /// Spread of the values reported at rest.
+    pub flat: i32,
+    /// Range advertised by the device, which calibrated values are scaled
+    /// to.
+    pub range: (i32, i32),
+}
+
+impl AxisCalibration {
+    /// Map a raw value onto `range`, with the rest position going to the
+    /// middle of it.
+    pub fn apply(&self, raw: i32) -> i32 {
+        let (low, high) = (self.range.0 as f64, self.range.1 as f64);
+        let scale = |value: i32, from: i32, to: i32, out_from: f64, out_to: f64| {
+            let span = (to as f64 - from as f64).max(1.0);
+            out_from + (value as f64 - from as f64) / span * (out_to - out_from)
+        };
+        let raw = raw.clamp(self.minimum, self.maximum);
+        let value = match self.center {
+            Some(center) if raw < center => scale(raw, self.minimum, center, low, (low + high) / 2.0),
+            Some(center) => scale(raw, center, self.maximum, (low + high) / 2.0, high),
+            None => scale(raw, self.minimum, self.maximum, low, high),
+        };
+        value.round() as i32
+    }
+}
+
+/// Calibration of the axes of one device, keyed by `ABS_*` code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationProfile {
+    pub device: StableId,
+    pub axes: BTreeMap<u16, AxisCalibration>,
+}
+
+impl CalibrationProfile {
+    /// Calibration of the axis of an axis event.
+    pub fn axis<T>(&self, event: &Event<T>) -> Option<&AxisCalibration> where T: TryFrom<i32> {
+        self.axes.get(&abs_code(event)?)
+    }
+
+    /// Push the calibrated ranges to the kernel (`EVIOCSABS`), so every
+    /// reader of the device sees them.  The kernel has no notion of an
+    /// off-center rest position, so only the range and flat are set.
+    pub fn write_to(&self, device: &Device) -> io::Result<()> {
+        for (&code, axis) in &self.axes {
+            let info = device.absinfo(code)?;
+            device.set_absinfo(code, AbsInfo { minimum: axis.minimum, maximum: axis.maximum, flat: axis.flat, ..info })?;
+        }
+        Ok(())
+    }
+
+    /// Write the profile in the text format of this module.
+    pub fn save(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "{HEADER}")?;
+        writeln!(out, "device {}", self.device)?;
+        for (code, axis) in &self.axes {
+            let center = axis.center.map_or("-".to_owned(), |center| center.to_string());
+            writeln!(out, "axis {code:02x} {} {center} {} {} {} {}",
+                axis.minimum, axis.maximum, axis.flat, axis.range.0, axis.range.1)?;
+        }
+        out.flush()
+    }
+
+    /// Read a profile written by [`CalibrationProfile::save`].
+    pub fn load(input: impl BufRead) -> io::Result<Self> {
+        let invalid = |line: &str| io::Error::new(io::ErrorKind::InvalidData, format!("invalid calibration line: {line}"));
+        let mut device = None;
+        let mut axes = BTreeMap::new();
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(id) = line.strip_prefix("device ") {
+                device = Some(id.trim().parse()?);
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let &["axis", code, minimum, center, maximum, flat, low, high] = fields.as_slice() else {
+                return Err(invalid(line));
+            };
+            let number = |field: &str| field.parse::<i32>().map_err(|_| invalid(line));
+            let code = u16::from_str_radix(code, 16).map_err(|_| invalid(line))?;
+            let center = if center == "-" { None } else { Some(number(center)?) };
+            let axis = AxisCalibration {
+                minimum: number(minimum)?,
+                center,
+                maximum: number(maximum)?,
+                flat: number(flat)?,
+                range: (number(low)?, number(high)?),
+            };
+            axes.insert(code, axis);
+        }
+        let device = device.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing device line"))?;
+        Ok(Self { device, axes })
+    }
+
+    /// A pipeline stage applying the calibration in software.
+    pub fn stage(&self) -> Calibrate {
+        Calibrate { axes: self.axes.clone() }
+    }
+}
+
+const HEADER: &str = "# linux-input-event calibration 1";
+
+/// Applies the axes of a [`CalibrationProfile`] to axis events.
+#[derive(Debug, Clone)]
+pub struct Calibrate {
+    axes: BTreeMap<u16, AxisCalibration>,
+}
+
+impl<T> Stage<T> for Calibrate where T: TryFrom<i32> + Copy + Into<f64> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let calibrated = abs_code(&event).zip(event.value()).and_then(|(code, value)| {
+            let axis = self.axes.get(&code)?;
+            let value = axis.apply((*value).into().round() as i32);
+            Some(event.with_value(T::try_from(value).ok()?))
+        });
+        out.push(calibrated.unwrap_or(event));
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AxisSamples {
+    range: (i32, i32),
+    rest: Vec<i32>,
+    minimum: i32,
+    maximum: i32,
+    last: i32,
+}
+
+/// Walks through the [`CalibrationStep`]s, collecting axis values.
+///
+/// Feed it every event of the device and call [`Calibrator::advance`] when
+/// the user has finished the current step.  Axes that weren't moved during
+/// [`CalibrationStep::Extents`] are left out of the profile.
+#[derive(Debug, Clone)]
+pub struct Calibrator {
+    step: CalibrationStep,
+    axes: BTreeMap<u16, AxisSamples>,
+}
+
+impl Calibrator {
+    /// Calibrate the axes with the given advertised ranges.
+    pub fn new(axes: impl IntoIterator<Item = (u16, AbsInfo)>) -> Self {
+        let axes = axes.into_iter().map(|(code, info)| {
+            let samples = AxisSamples {
+                range: (info.minimum, info.maximum),
+                rest: vec![info.value],
+                minimum: info.value,
+                maximum: info.value,
+                last: info.value,
+            };
+            (code, samples)
+        }).collect();
+        Self { step: CalibrationStep::Center, axes }
+    }
+
+    /// Calibrate every analog axis of a device.
+    pub fn for_device(device: &Device) -> io::Result<Self> {
+        let mut axes = Vec::new();
+        // The axes below the hats.
+        for code in 0..ABS_HAT0X as u16 {
+            if device.has(EV_ABS as _, code)? {
+                axes.push((code, device.absinfo(code)?));
+            }
+        }
+        Ok(Self::new(axes))
+    }
+
+    pub fn step(&self) -> CalibrationStep {
+        self.step
+    }
+
+    /// Finish the current step and move on to the next one.
+    pub fn advance(&mut self) -> CalibrationStep {
+        self.step = match self.step {
+            CalibrationStep::Center => {
+                for axis in self.axes.values_mut() {
+                    (axis.minimum, axis.maximum) = (axis.last, axis.last);
+                }
+                CalibrationStep::Extents
+            }
+            CalibrationStep::Extents => CalibrationStep::Release,
+            CalibrationStep::Release | CalibrationStep::Done => CalibrationStep::Done,
+        };
+        self.step
+    }
+
+    pub fn feed<T>(&mut self, event: &Event<T>) where T: TryFrom<i32> + Copy + Into<f64> {
+        if let (Some(code), Some(value)) = (abs_code(event), event.value()) {
+            self.feed_raw(code, (*value).into().round() as i32);
+        }
+    }
+
+    /// Feed a raw `EV_ABS` value.
+    pub fn feed_raw(&mut self, code: u16, value: i32) {
+        let Some(axis) = self.axes.get_mut(&code) else {
+            return;
+        };
+        axis.last = value;
+        match self.step {
+            CalibrationStep::Center => axis.rest.push(value),
+            CalibrationStep::Extents => {
+                axis.minimum = axis.minimum.min(value);
+                axis.maximum = axis.maximum.max(value);
+            }
+            // Only where the axis settles counts, not its way back.
+            CalibrationStep::Release | CalibrationStep::Done => {}
+        }
+    }
+
+    /// The measured profile, once all steps are done.
+    pub fn finish(&self, device: StableId) -> Option<CalibrationProfile> {
+        if self.step != CalibrationStep::Done {
+            return None;
+        }
+        let axes = self.axes.iter().filter_map(|(&code, axis)| {
+            let advertised = (axis.range.1 as i64 - axis.range.0 as i64).max(1);
+            // Less than a tenth of the range: the axis wasn't touched.
+            if (axis.maximum as i64 - axis.minimum as i64) * 10 < advertised {
+                return None;
+            }
+            // Where the axis settled after release counts as a rest sample.
+            let rest: Vec<i32> = axis.rest.iter().copied().chain([axis.last]).collect();
+            let rest_min = rest.iter().copied().min().unwrap_or(axis.last);
+            let rest_max = rest.iter().copied().max().unwrap_or(axis.last);
+            let mean = (rest.iter().map(|v| *v as i64).sum::<i64>() / rest.len() as i64) as i32;
+            let minimum = axis.minimum.min(rest_min);
+            let maximum = axis.maximum.max(rest_max);
+            // Resting within a tenth of the range from an end: not a
+            // self-centering axis.
+            let near_end = |end: i32| (mean as i64 - end as i64).abs() * 10 < maximum as i64 - minimum as i64;
+            let center = (!near_end(minimum) && !near_end(maximum)).then_some(mean);
+            let calibration = AxisCalibration {
+                minimum,
+                center,
+                maximum,
+                flat: rest_max - rest_min,
+                range: axis.range,
+            };
+            Some((code, calibration))
+        }).collect();
+        Some(CalibrationProfile { device, axes })
+    }
+}
+
+/// `ABS_*` code of the analog axis events decoded from `EV_ABS`.
+fn abs_code<T>(event: &Event<T>) -> Option<u16> where T: TryFrom<i32> {
+    let Event::Axis(axis, _) = event else {
+        return None;
+    };
+    match axis.kind().codes() {
+        &[(type_, code)] if type_ == EV_ABS as u16 => Some(code),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Axis;
+
+    fn info(minimum: i32, maximum: i32, value: i32) -> AbsInfo {
+        AbsInfo { value, minimum, maximum, ..AbsInfo::default() }
+    }
+
+    fn device() -> StableId {
+        StableId::Serial { vendor: 0x045e, product: 0x028e, uniq: "3032363030".to_owned(), node: 0 }
+    }
+
+    fn calibrate() -> Calibrator {
+        let mut calibrator = Calibrator::new([
+            (0x00, info(-32768, 32767, 0)),
+            (0x01, info(-32768, 32767, 0)),
+            (0x06, info(0, 255, 0)),
+        ]);
+        for value in [10, -10, 5] {
+            calibrator.feed_raw(0x00, value);
+        }
+        calibrator.feed_raw(0x06, 0);
+        assert_eq!(calibrator.advance(), CalibrationStep::Extents);
+        for value in [-30000, 31000] {
+            calibrator.feed_raw(0x00, value);
+        }
+        calibrator.feed_raw(0x01, 200);
+        calibrator.feed(&Event::<i32>::Axis(Axis::Throttle, 255));
+        assert_eq!(calibrator.advance(), CalibrationStep::Release);
+        calibrator.feed_raw(0x00, 2);
+        calibrator.feed_raw(0x06, 1);
+        calibrator
+    }
+
+    #[test]
+    fn finish_measures_moved_axes() {
+        let mut calibrator = calibrate();
+        assert_eq!(calibrator.finish(device()), None);
+        assert_eq!(calibrator.advance(), CalibrationStep::Done);
+        let profile = calibrator.finish(device()).unwrap();
+        let stick = AxisCalibration { minimum: -30000, center: Some(1), maximum: 31000, flat: 20, range: (-32768, 32767) };
+        assert_eq!(profile.axes[&0x00], stick);
+        // Barely moved.
+        assert!(!profile.axes.contains_key(&0x01));
+        // Rests at an end.
+        let throttle = AxisCalibration { minimum: 0, center: None, maximum: 255, flat: 1, range: (0, 255) };
+        assert_eq!(profile.axes[&0x06], throttle);
+    }
+
+    #[test]
+    fn apply_centered() {
+        let axis = AxisCalibration { minimum: -800, center: Some(100), maximum: 900, flat: 0, range: (-1000, 1000) };
+        assert_eq!(axis.apply(100), 0);
+        assert_eq!(axis.apply(-800), -1000);
+        assert_eq!(axis.apply(900), 1000);
+        assert_eq!(axis.apply(-350), -500);
+        assert_eq!(axis.apply(500), 500);
+        assert_eq!(axis.apply(-5000), -1000);
+        assert_eq!(axis.apply(5000), 1000);
+    }
+
+    #[test]
+    fn apply_uncentered() {
+        let axis = AxisCalibration { minimum: 10, center: None, maximum: 210, flat: 0, range: (0, 255) };
+        assert_eq!(axis.apply(0), 0);
+        assert_eq!(axis.apply(10), 0);
+        assert_eq!(axis.apply(110), 128);
+        assert_eq!(axis.apply(210), 255);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut calibrator = calibrate();
+        calibrator.advance();
+        let profile = calibrator.finish(device()).unwrap();
+        let mut saved = Vec::new();
+        profile.save(&mut saved).unwrap();
+        let text = String::from_utf8(saved).unwrap();
+        assert_eq!(text, "# linux-input-event calibration 1\n\
+            device serial:045e:028e:0:3032363030\n\
+            axis 00 -30000 1 31000 20 -32768 32767\n\
+            axis 06 0 - 255 1 0 255\n");
+        assert_eq!(CalibrationProfile::load(text.as_bytes()).unwrap(), profile);
+    }
+
+    #[test]
+    fn load_rejects_garbage() {
+        assert!(CalibrationProfile::load("axis 00 0 - 10 0 0 10\n".as_bytes()).is_err());
+        assert!(CalibrationProfile::load("device phys:usb-1\naxis 00 0 - 10\n".as_bytes()).is_err());
+        assert!(CalibrationProfile::load("device phys:usb-1\naxis zz 0 - 10 0 0 10\n".as_bytes()).is_err());
+        assert!(CalibrationProfile::load("device nonsense\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Detection of buttons pressed together, such as `MenuL + MenuR`.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::time::Timestamp;
+use crate::{Button, Event};
+
+/// Handle of a chord registered with [`ChordDetector::add`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChordId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChordEvent {
+    /// All buttons of the chord went down within the window.
+    Pressed(ChordId),
+    /// The first button of an active chord was released.
+    Released(ChordId),
+}
+
+/// What a [`ChordDetector`] emits: the events it was fed, in order, with
+/// chord events in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChordOutput<T = i32> where T: TryFrom<i32> {
+    Event(Event<T>),
+    Chord(ChordEvent),
+}
+
+#[derive(Debug)]
+struct Chord {
+    buttons: Vec<Button>,
+    suppress: bool,
+    active: bool,
+    /// Buttons of a fired suppressing chord whose release is still to be
+    /// swallowed.
+    swallow: Vec<Button>,
+}
+
+#[derive(Debug)]
+struct Pending<T> where T: TryFrom<i32> {
+    id: Button,
+    deadline: Timestamp,
+    press: Event<T>,
+}
+
+/// Detects chords of buttons pressed within a time window of each other.
+///
+/// Time comes from the timestamps passed to [`ChordDetector::feed`].  Presses
+/// of buttons belonging to a suppressing chord are held back for up to the
+/// window, and dropped if the chord fires; call [`ChordDetector::poll`] by
+/// [`ChordDetector::next_deadline`] to get held back presses out in time
+/// when no further events arrive.
+#[derive(Debug)]
+pub struct ChordDetector<T = i32> where T: TryFrom<i32> {
+    window: Duration,
+    chords: Vec<Chord>,
+    held: HashMap<Button, Timestamp>,
+    pending: Vec<Pending<T>>,
+}
+
+impl<T> ChordDetector<T> where T: TryFrom<i32> {
+    pub fn new(window: Duration) -> Self {
+        Self { window, chords: Vec::new(), held: HashMap::new(), pending: Vec::new() }
+    }
+
+    /// Register a chord of `buttons`.  With `suppress`, the component button
+    /// events are not passed on when the chord fires.
+    pub fn add(&mut self, buttons: &[Button], suppress: bool) -> ChordId {
+        let mut ids = buttons.to_vec();
+        ids.sort();
+        ids.dedup();
+        self.chords.push(Chord { buttons: ids, suppress, active: false, swallow: Vec::new() });
+        ChordId(self.chords.len() - 1)
+    }
+
+    pub fn is_active(&self, chord: ChordId) -> bool {
+        self.chords.get(chord.0).is_some_and(|chord| chord.active)
+    }
+
+    /// When held back presses have to be flushed, if there are any.
+    pub fn next_deadline(&self) -> Option<Timestamp> {
+        self.pending.iter().map(|pending| pending.deadline).min()
+    }
+
+    /// Pass on held back presses whose window has ended by `now`.
+    pub fn poll(&mut self, now: Timestamp) -> Vec<ChordOutput<T>> {
+        let mut out = Vec::new();
+        self.flush(now, &mut out);
+        out
+    }
+
+    pub fn feed(&mut self, time: Timestamp, event: Event<T>) -> Vec<ChordOutput<T>> {
+        let mut out = Vec::new();
+        self.flush(time, &mut out);
+        match (event.button(), event.pressed()) {
+            (Some(id), Some(true)) => self.press(time, id, event, &mut out),
+            (Some(id), Some(false)) => self.release(id, event, &mut out),
+            _ => out.push(ChordOutput::Event(event)),
+        }
+        out
+    }
+
+    fn flush(&mut self, now: Timestamp, out: &mut Vec<ChordOutput<T>>) {
+        let mut i = 0;
+        while i < self.pending.len() {
+            if self.pending[i].deadline <= now {
+                out.push(ChordOutput::Event(self.pending.remove(i).press));
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn press(&mut self, time: Timestamp, id: Button, event: Event<T>, out: &mut Vec<ChordOutput<T>>) {
+        self.held.insert(id, time);
+        let mut swallowed = false;
+        for (index, chord) in self.chords.iter_mut().enumerate() {
+            if chord.active || !chord.buttons.contains(&id) {
+                continue;
+            }
+            let times: Option<Vec<Timestamp>> = chord.buttons.iter().map(|b| self.held.get(b).copied()).collect();
+            let Some(times) = times else {
+                continue;
+            };
+            let first = times.iter().min().copied().unwrap_or(time);
+            if time.duration_since(first) > self.window {
+                continue;
+            }
+            chord.active = true;
+            out.push(ChordOutput::Chord(ChordEvent::Pressed(ChordId(index))));
+            if chord.suppress {
+                // Presses already passed on keep their releases.
+                chord.swallow = self.pending.iter().map(|pending| pending.id).filter(|b| chord.buttons.contains(b)).collect();
+                chord.swallow.push(id);
+                self.pending.retain(|pending| !chord.buttons.contains(&pending.id));
+                swallowed = true;
+            }
+        }
+        if swallowed {
+            return;
+        }
+        let held_back = self.chords.iter().any(|chord| chord.suppress && chord.buttons.contains(&id));
+        if held_back {
+            self.pending.push(Pending { id, deadline: time + self.window, press: event });
+        } else {
+            out.push(ChordOutput::Event(event));
+        }
+    }
+
+    fn release(&mut self, id: Button, event: Event<T>, out: &mut Vec<ChordOutput<T>>) {
+        self.held.remove(&id);
+        // A tap shorter than the window still gets its press out first.
+        if let Some(index) = self.pending.iter().position(|pending| pending.id == id) {
+            out.push(ChordOutput::Event(self.pending.remove(index).press));
+        }
+        let mut swallowed = false;
+        for (index, chord) in self.chords.iter_mut().enumerate() {
+            if chord.active && chord.buttons.contains(&id) {
+                chord.active = false;
+                out.push(ChordOutput::Chord(ChordEvent::Released(ChordId(index))));
+            }
+            if let Some(position) = chord.swallow.iter().position(|b| *b == id) {
+                chord.swallow.remove(position);
+                swallowed = true;
+            }
+        }
+        if !swallowed {
+            out.push(ChordOutput::Event(event));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{at, MS};
+
+    fn detector(suppress: bool) -> (ChordDetector, ChordId) {
+        let mut detector = ChordDetector::new(50 * MS);
+        let chord = detector.add(&[Button::MenuL, Button::MenuR], suppress);
+        (detector, chord)
+    }
+
+    fn menu_l(pushed: bool) -> Event {
+        Event::Button(Button::MenuL, pushed)
+    }
+
+    fn menu_r(pushed: bool) -> Event {
+        Event::Button(Button::MenuR, pushed)
+    }
+
+    fn pressed(chord: ChordId) -> ChordOutput {
+        ChordOutput::Chord(ChordEvent::Pressed(chord))
+    }
+
+    fn released(chord: ChordId) -> ChordOutput {
+        ChordOutput::Chord(ChordEvent::Released(chord))
+    }
+
+    #[test]
+    fn chord_within_window() {
+        let (mut detector, chord) = detector(false);
+        assert_eq!(detector.feed(at(0), menu_l(true)), [ChordOutput::Event(menu_l(true))]);
+        assert_eq!(detector.feed(at(30), menu_r(true)), [pressed(chord), ChordOutput::Event(menu_r(true))]);
+        assert!(detector.is_active(chord));
+        assert_eq!(detector.feed(at(100), menu_l(false)), [released(chord), ChordOutput::Event(menu_l(false))]);
+    }
+
+    #[test]
+    fn suppressed_chord() {
+        let (mut detector, chord) = detector(true);
+        assert!(detector.feed(at(0), menu_l(true)).is_empty());
+        assert_eq!(detector.feed(at(10), menu_r(true)), [pressed(chord)]);
+        assert_eq!(detector.next_deadline(), None);
+        assert_eq!(detector.feed(at(100), menu_r(false)), [released(chord)]);
+        assert!(detector.feed(at(110), menu_l(false)).is_empty());
+    }
+
+    #[test]
+    fn poll_at_deadline_flushes() {
+        let (mut detector, _) = detector(true);
+        assert!(detector.feed(at(0), menu_l(true)).is_empty());
+        let deadline = detector.next_deadline().unwrap();
+        assert_eq!(deadline, at(50));
+        assert!(detector.poll(at(49)).is_empty());
+        assert_eq!(detector.poll(deadline), [ChordOutput::Event(menu_l(true))]);
+        assert_eq!(detector.next_deadline(), None);
+        assert!(detector.poll(deadline).is_empty());
+    }
+
+    #[test]
+    fn chord_completed_at_deadline_keeps_flushed_release() {
+        let (mut detector, chord) = detector(true);
+        detector.feed(at(0), menu_l(true));
+        assert_eq!(detector.poll(at(50)), [ChordOutput::Event(menu_l(true))]);
+        assert_eq!(detector.feed(at(50), menu_r(true)), [pressed(chord)]);
+        assert_eq!(detector.feed(at(60), menu_l(false)), [released(chord), ChordOutput::Event(menu_l(false))]);
+        assert!(detector.feed(at(70), menu_r(false)).is_empty());
+    }
+
+    #[test]
+    fn tap_shorter_than_window() {
+        let (mut detector, _) = detector(true);
+        assert!(detector.feed(at(0), menu_l(true)).is_empty());
+        assert_eq!(detector.feed(at(20), menu_l(false)), [ChordOutput::Event(menu_l(true)), ChordOutput::Event(menu_l(false))]);
+    }
+
+    #[test]
+    fn repeated_buttons_count_once() {
+        let mut detector = ChordDetector::new(50 * MS);
+        let chord = detector.add(&[Button::MenuR, Button::MenuL, Button::MenuR], true);
+        assert_eq!(detector.chords[chord.0].buttons, [Button::MenuL, Button::MenuR]);
+        assert!(detector.feed(at(0), menu_r(true)).is_empty());
+        assert_eq!(detector.feed(at(10), menu_l(true)), [pressed(chord)]);
+    }
+}
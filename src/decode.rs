@@ -0,0 +1,187 @@
+//! Decoding that follows the properties of a device.
+//!
+//! The same codes mean different things depending on the device: `ABS_X`
+//! is a stick on a gamepad, a touch position on a touchscreen or tablet and
+//! acceleration on a motion node.  [`Decoder`] picks the interpretation from
+//! the [`InputProps`] of the device.
+
+use std::io;
+
+use input_linux_sys::input_event;
+
+use crate::{Device, Event, HatState, InputProp, InputProps, MotionDecoder, MotionEvent, TabletConfig, TabletDecoder, TabletEvent};
+
+/// How the events of a device are read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpretation {
+    /// As [`Event`]s.  Also used for pointing sticks, whose relative
+    /// motion maps onto the mouse events.
+    Gamepad,
+    /// Absolute positions on a surface, through a [`TabletDecoder`]:
+    /// touchscreens, touchpads and tablets.
+    Pointer,
+    /// Acceleration and angular velocity, through a [`MotionDecoder`].
+    Motion,
+}
+
+impl Interpretation {
+    /// The interpretation for a set of properties.  `ButtonPad`,
+    /// `TopButtonPad` and `SemiMt` are only set on touchpads, so they make a
+    /// pointer of a device even if its driver forgot `Pointer`.  Semi-MT pads
+    /// need no special care, as only the single-touch position is read,
+    /// which they report exactly.
+    pub fn of(props: InputProps) -> Self {
+        let touchpad = [InputProp::Pointer, InputProp::ButtonPad, InputProp::TopButtonPad, InputProp::SemiMt];
+        if props.contains(InputProp::Accelerometer) {
+            Interpretation::Motion
+        } else if props.contains(InputProp::PointingStick) {
+            Interpretation::Gamepad
+        } else if props.contains(InputProp::Direct) || touchpad.into_iter().any(|prop| props.contains(prop)) {
+            Interpretation::Pointer
+        } else {
+            Interpretation::Gamepad
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecodedEvent<T = i32> where T: TryFrom<i32> {
+    Gamepad(Event<T>),
+    Pointer(TabletEvent),
+    Motion(MotionEvent),
+}
+
+#[derive(Debug, Clone)]
+enum Inner {
+    Gamepad { hat_state: HatState },
+    Pointer(TabletDecoder),
+    Motion(MotionDecoder),
+}
+
+/// Decodes the raw events of a device according to its [`Interpretation`].
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    inner: Inner,
+}
+
+impl Decoder {
+    /// Decode as [`Interpretation::Gamepad`], which needs nothing from the
+    /// device.
+    pub fn gamepad() -> Self {
+        Self { inner: Inner::Gamepad { hat_state: HatState::new() } }
+    }
+
+    /// Decode a device as its properties say, with `config` used if it is a
+    /// pointer device.
+    pub fn for_device(device: &Device, config: TabletConfig) -> io::Result<Self> {
+        let interpretation = Interpretation::of(device.props()?);
+        Self::with_interpretation(device, interpretation, config)
+    }
+
+    /// Decode a device with the given interpretation, whatever its
+    /// properties, e.g. for devices whose driver doesn't set them.
+    pub fn with_interpretation(device: &Device, interpretation: Interpretation, config: TabletConfig) -> io::Result<Self> {
+        let inner = match interpretation {
+            Interpretation::Gamepad => return Ok(Self::gamepad()),
+            Interpretation::Pointer => Inner::Pointer(TabletDecoder::for_device(device, config)?),
+            Interpretation::Motion => Inner::Motion(MotionDecoder::for_device(device)?),
+        };
+        Ok(Self { inner })
+    }
+
+    pub fn interpretation(&self) -> Interpretation {
+        match self.inner {
+            Inner::Gamepad { .. } => Interpretation::Gamepad,
+            Inner::Pointer(_) => Interpretation::Pointer,
+            Inner::Motion(_) => Interpretation::Motion,
+        }
+    }
+
+    /// Handle one raw event.  Gamepad events come out right away, pointer
+    /// and motion events at the end of their frame.
+    pub fn feed<T>(&mut self, raw: &input_event) -> Vec<DecodedEvent<T>> where T: TryFrom<i32> {
+        match &mut self.inner {
+            Inner::Gamepad { hat_state } => Event::from_input_event(raw, hat_state).map(DecodedEvent::Gamepad).into_iter().collect(),
+            Inner::Pointer(decoder) => decoder.feed(raw).into_iter().map(DecodedEvent::Pointer).collect(),
+            Inner::Motion(decoder) => decoder.feed(raw).into_iter().map(DecodedEvent::Motion).collect(),
+        }
+    }
+}
+
+impl From<TabletDecoder> for Decoder {
+    fn from(decoder: TabletDecoder) -> Self {
+        Self { inner: Inner::Pointer(decoder) }
+    }
+}
+
+impl From<MotionDecoder> for Decoder {
+    fn from(decoder: MotionDecoder) -> Self {
+        Self { inner: Inner::Motion(decoder) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use input_linux_sys::*;
+
+    use super::*;
+    use crate::{AbsInfo, Axis, Button};
+
+    #[test]
+    fn interpretation_table() {
+        use InputProp::*;
+        let table: [(&[InputProp], Interpretation); 11] = [
+            (&[], Interpretation::Gamepad),
+            (&[Pointer], Interpretation::Pointer),
+            (&[Direct], Interpretation::Pointer),
+            (&[ButtonPad], Interpretation::Pointer),
+            (&[TopButtonPad], Interpretation::Pointer),
+            (&[SemiMt], Interpretation::Pointer),
+            (&[Pointer, ButtonPad, SemiMt], Interpretation::Pointer),
+            (&[PointingStick], Interpretation::Gamepad),
+            (&[Pointer, PointingStick], Interpretation::Gamepad),
+            (&[Accelerometer], Interpretation::Motion),
+            (&[Direct, Accelerometer], Interpretation::Motion),
+        ];
+        for (props, expected) in table {
+            assert_eq!(Interpretation::of(props.iter().copied().collect()), expected, "{props:?}");
+        }
+    }
+
+    fn raw(type_: i32, code: i32, value: i32) -> input_event {
+        input_event { time: timeval { tv_sec: 0, tv_usec: 0 }, type_: type_ as u16, code: code as u16, value }
+    }
+
+    /// The same frame through each interpretation.
+    fn decode(mut decoder: Decoder) -> Vec<DecodedEvent> {
+        [raw(EV_ABS, ABS_X, 512), raw(EV_KEY, BTN_SOUTH, 1), raw(EV_SYN, SYN_REPORT, 0)]
+            .iter()
+            .flat_map(|event| decoder.feed(event))
+            .collect()
+    }
+
+    #[test]
+    fn decoder_interpretations() {
+        assert_eq!(Decoder::gamepad().interpretation(), Interpretation::Gamepad);
+        assert_eq!(decode(Decoder::gamepad()), [
+            DecodedEvent::Gamepad(Event::Axis(Axis::JoyX, 512)),
+            DecodedEvent::Gamepad(Event::Button(Button::ActionA, true)),
+        ]);
+
+        let info = AbsInfo { minimum: 0, maximum: 1024, resolution: 256, ..AbsInfo::default() };
+        let axes = [(ABS_X as u16, info), (ABS_Y as u16, info)];
+        let decoder = Decoder::from(TabletDecoder::new(axes, TabletConfig::new()));
+        assert_eq!(decoder.interpretation(), Interpretation::Pointer);
+        assert_eq!(decode(decoder), [
+            DecodedEvent::Pointer(TabletEvent::Position { x: 0.5, y: 0.0 }),
+            DecodedEvent::Pointer(TabletEvent::Key(BTN_SOUTH as u16, true)),
+        ]);
+
+        let decoder = Decoder::from(MotionDecoder::new(axes));
+        assert_eq!(decoder.interpretation(), Interpretation::Motion);
+        let x = 2.0 * crate::motion::STANDARD_GRAVITY;
+        assert_eq!(decode(decoder), [DecodedEvent::Motion(MotionEvent::Accel { x, y: 0.0, z: 0.0 })]);
+    }
+}
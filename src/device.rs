@@ -0,0 +1,470 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem::{size_of, zeroed};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+
+use input_linux_sys::*;
+use nix::libc::{c_char, ioctl};
+use nix::{convert_ioctl_res, request_code_read, request_code_write};
+
+use crate::{Event, HatState, Identity};
+
+/// An open evdev device node (`/dev/input/eventN`).
+#[derive(Debug)]
+pub struct Device {
+    file: File,
+    hat_state: HatState,
+    disconnected: bool,
+}
+
+/// Kernel autorepeat settings of a device (`EVIOCGREP` / `EVIOCSREP`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepeatSettings {
+    /// Delay before a held key starts repeating (milliseconds)
+    pub delay: u32,
+    /// Period between repeats (milliseconds)
+    pub period: u32,
+}
+
+/// Bus type, vendor, product and version of a device (`EVIOCGID`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputId {
+    pub bustype: u16,
+    pub vendor: u16,
+    pub product: u16,
+    pub version: u16,
+}
+
+/// Range and current value of an absolute axis (`EVIOCGABS`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AbsInfo {
+    pub value: i32,
+    pub minimum: i32,
+    pub maximum: i32,
+    pub fuzz: i32,
+    pub flat: i32,
+    pub resolution: i32,
+}
+
+impl From<input_absinfo> for AbsInfo {
+    fn from(info: input_absinfo) -> Self {
+        Self {
+            value: info.value,
+            minimum: info.minimum,
+            maximum: info.maximum,
+            fuzz: info.fuzz,
+            flat: info.flat,
+            resolution: info.resolution,
+        }
+    }
+}
+
+impl From<AbsInfo> for input_absinfo {
+    fn from(info: AbsInfo) -> Self {
+        Self {
+            value: info.value,
+            minimum: info.minimum,
+            maximum: info.maximum,
+            fuzz: info.fuzz,
+            flat: info.flat,
+            resolution: info.resolution,
+        }
+    }
+}
+
+/// A device property (`INPUT_PROP_*`), telling how its events are meant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputProp {
+    /// Moves a pointer rather than touching the screen, e.g. a touchpad or
+    /// a tablet without a display.
+    Pointer = INPUT_PROP_POINTER as isize,
+    /// Touches the screen directly, e.g. a touchscreen or a display tablet.
+    Direct = INPUT_PROP_DIRECT as isize,
+    /// The touchpad surface is the button.
+    ButtonPad = INPUT_PROP_BUTTONPAD as isize,
+    /// Multitouch with a bounding box instead of the touch positions.
+    SemiMt = INPUT_PROP_SEMI_MT as isize,
+    /// Soft buttons along the top of a touchpad.
+    TopButtonPad = INPUT_PROP_TOPBUTTONPAD as isize,
+    /// A trackpoint.
+    PointingStick = INPUT_PROP_POINTING_STICK as isize,
+    /// Axes report acceleration and angular velocity, see
+    /// [`crate::motion`].
+    Accelerometer = INPUT_PROP_ACCELEROMETER as isize,
+}
+
+impl InputProp {
+    pub const ALL: [InputProp; 7] = [
+        InputProp::Pointer, InputProp::Direct, InputProp::ButtonPad, InputProp::SemiMt,
+        InputProp::TopButtonPad, InputProp::PointingStick, InputProp::Accelerometer,
+    ];
+}
+
+/// The properties of a device (`EVIOCGPROP`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InputProps(u32);
+
+impl InputProps {
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// From a raw `INPUT_PROP_*` bitmask.  Unknown properties are kept.
+    pub fn from_bitmask(mask: &[u8]) -> Self {
+        let bits = (0..32).filter(|&bit| bit_is_set(mask, bit)).fold(0, |bits, bit| bits | 1 << bit);
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn contains(self, prop: InputProp) -> bool {
+        self.0 & 1 << prop as u32 != 0
+    }
+
+    pub fn insert(&mut self, prop: InputProp) {
+        self.0 |= 1 << prop as u32;
+    }
+
+    pub fn remove(&mut self, prop: InputProp) {
+        self.0 &= !(1 << prop as u32);
+    }
+
+    pub const fn with(mut self, prop: InputProp) -> Self {
+        self.0 |= 1 << prop as u32;
+        self
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The known properties that are set.
+    pub fn iter(self) -> impl Iterator<Item = InputProp> {
+        InputProp::ALL.into_iter().filter(move |prop| self.contains(*prop))
+    }
+}
+
+impl FromIterator<InputProp> for InputProps {
+    fn from_iter<I: IntoIterator<Item = InputProp>>(iter: I) -> Self {
+        iter.into_iter().fold(Self::empty(), Self::with)
+    }
+}
+
+impl Device {
+    /// Open a device node for reading and writing.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self::from_file(file))
+    }
+
+    pub fn from_file(file: File) -> Self {
+        Self { file, hat_state: HatState::new(), disconnected: false }
+    }
+
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn into_file(self) -> File {
+        self.file
+    }
+
+    /// Block until the next raw event is available.
+    pub fn read_event(&mut self) -> io::Result<input_event> {
+        // Safety: input_event is plain old data, any bit pattern is valid.
+        let mut event: input_event = unsafe { zeroed() };
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(&mut event as *mut input_event as *mut u8, size_of::<input_event>())
+        };
+        self.file.read_exact(buf)?;
+        Ok(event)
+    }
+
+    /// Read whatever raw events are available, up to `buf.len()`.
+    pub(crate) fn read_raw(&mut self, buf: &mut [input_event]) -> io::Result<usize> {
+        // Safety: as above, and evdev only ever returns whole events.
+        let bytes = unsafe {
+            std::slice::from_raw_parts_mut(buf.as_mut_ptr() as *mut u8, std::mem::size_of_val(buf))
+        };
+        match self.file.read(bytes)? {
+            0 => Err(io::ErrorKind::UnexpectedEof.into()),
+            n => Ok(n / size_of::<input_event>()),
+        }
+    }
+
+    /// Decode a raw event read from this device, tracking its hat state.
+    pub(crate) fn decode<T>(&mut self, raw: &input_event) -> Option<Event<T>> where T: TryFrom<i32> {
+        Event::from_input_event(raw, &mut self.hat_state)
+    }
+
+    /// Block until the next decoded event.  Once the device goes away this
+    /// returns [`Event::Disconnect`] a single time, and errors afterwards.
+    pub fn next_event<T>(&mut self) -> io::Result<Event<T>> where T: TryFrom<i32> {
+        if self.disconnected {
+            return Err(io::Error::from_raw_os_error(nix::libc::ENODEV));
+        }
+        loop {
+            match self.read_event() {
+                Ok(raw) => {
+                    if let Some(event) = self.decode(&raw) {
+                        return Ok(event);
+                    }
+                }
+                Err(err) if is_disconnect(&err) => {
+                    self.disconnected = true;
+                    return Ok(Event::Disconnect);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Block until the end of the next frame (`SYN_REPORT`) and return its
+    /// decoded events, which may be none.  Frames cut short by the kernel
+    /// dropping events (`SYN_DROPPED`) are skipped.  A device that goes away
+    /// gives a frame of just [`Event::Disconnect`], like
+    /// [`Device::next_event`].
+    pub fn next_frame<T>(&mut self) -> io::Result<Vec<Event<T>>> where T: TryFrom<i32> {
+        if self.disconnected {
+            return Err(io::Error::from_raw_os_error(nix::libc::ENODEV));
+        }
+        let mut frame = Vec::new();
+        let mut dropped = false;
+        loop {
+            let raw = match self.read_event() {
+                Ok(raw) => raw,
+                Err(err) if is_disconnect(&err) => {
+                    self.disconnected = true;
+                    return Ok(vec![Event::Disconnect]);
+                }
+                Err(err) => return Err(err),
+            };
+            match (raw.type_ as i32, raw.code as i32) {
+                (EV_SYN, SYN_REPORT) if dropped => dropped = false,
+                (EV_SYN, SYN_REPORT) => return Ok(frame),
+                (EV_SYN, SYN_DROPPED) => {
+                    frame.clear();
+                    dropped = true;
+                }
+                _ if dropped => {}
+                _ => frame.extend(self.decode(&raw)),
+            }
+        }
+    }
+
+    /// Whether [`Event::Disconnect`] has been returned.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    /// Write a raw event to the device.
+    pub fn write_event(&self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        write_event(&self.file, type_, code, value)
+    }
+
+    /// Drive the keyboard click, bell or PC speaker tone of the device with
+    /// an [`Event::Click`], [`Event::Bell`] or [`Event::Tone`].
+    pub fn play(&self, sound: &Event) -> io::Result<()> {
+        let (code, value) = match *sound {
+            Event::Click(on) => (SND_CLICK, on as i32),
+            Event::Bell(on) => (SND_BELL, on as i32),
+            Event::Tone(hz) => (SND_TONE, hz),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a sound event")),
+        };
+        self.write_event(EV_SND as _, code as _, value)?;
+        self.write_event(EV_SYN as _, SYN_REPORT as _, 0)
+    }
+
+    /// Current autorepeat delay and period.
+    pub fn repeat(&self) -> io::Result<RepeatSettings> {
+        let mut rep = repeat_settings { delay: 0, period: 0 };
+        check(unsafe { ev_get_rep(self.as_raw_fd(), &mut rep) })?;
+        Ok(RepeatSettings { delay: rep.delay, period: rep.period })
+    }
+
+    /// Change the autorepeat delay and period.
+    pub fn set_repeat(&self, settings: RepeatSettings) -> io::Result<()> {
+        let rep = repeat_settings { delay: settings.delay, period: settings.period };
+        check(unsafe { ev_set_rep(self.as_raw_fd(), &rep) })?;
+        Ok(())
+    }
+}
+
+impl Device {
+    /// Device name (`EVIOCGNAME`).
+    pub fn name(&self) -> io::Result<String> {
+        let mut buf = [0 as c_char; 256];
+        check(unsafe { ev_get_name(self.as_raw_fd(), &mut buf) })?;
+        Ok(c_string(&buf))
+    }
+
+    /// Physical location (`EVIOCGPHYS`), if the driver reports one.
+    pub fn phys(&self) -> io::Result<Option<String>> {
+        let mut buf = [0 as c_char; 256];
+        optional_string(unsafe { ev_get_phys(self.as_raw_fd(), &mut buf) }, &buf)
+    }
+
+    /// Unique identifier (`EVIOCGUNIQ`), if the driver reports one.
+    pub fn uniq(&self) -> io::Result<Option<String>> {
+        let mut buf = [0 as c_char; 256];
+        optional_string(unsafe { ev_get_uniq(self.as_raw_fd(), &mut buf) }, &buf)
+    }
+
+    pub fn identity(&self) -> io::Result<Identity> {
+        Identity::of(self)
+    }
+
+    /// Bus type, vendor, product and version (`EVIOCGID`).
+    pub fn id(&self) -> io::Result<InputId> {
+        let mut id = input_id { bustype: 0, vendor: 0, product: 0, version: 0 };
+        check(unsafe { ev_get_id(self.as_raw_fd(), &mut id) })?;
+        Ok(InputId { bustype: id.bustype, vendor: id.vendor, product: id.product, version: id.version })
+    }
+
+    /// Raw `INPUT_PROP_*` bitmask (`EVIOCGPROP`).
+    pub fn props_raw(&self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; bitmask_len(INPUT_PROP_CNT as _)];
+        check(unsafe { ev_get_prop(self.as_raw_fd(), &mut buf) })?;
+        Ok(buf)
+    }
+
+    /// Properties of the device (`EVIOCGPROP`).
+    pub fn props(&self) -> io::Result<InputProps> {
+        Ok(InputProps::from_bitmask(&self.props_raw()?))
+    }
+
+    /// Bitmask of the codes supported for an event type (`EVIOCGBIT`).  Type
+    /// `0` returns the bitmask of supported event types.
+    pub fn bits(&self, type_: u16) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; bitmask_len(code_count(type_))];
+        let request = request_code_read!(b'E', 0x20 + type_ as u32, buf.len());
+        check(unsafe { convert_ioctl_res!(ioctl(self.as_raw_fd(), request as _, buf.as_mut_ptr())) })?;
+        Ok(buf)
+    }
+
+    /// Whether the device supports the given event type and code.
+    pub fn has(&self, type_: u16, code: u16) -> io::Result<bool> {
+        Ok(bit_is_set(&self.bits(type_)?, code))
+    }
+
+    /// Range and current value of an absolute axis (`EVIOCGABS`).
+    pub fn absinfo(&self, code: u16) -> io::Result<AbsInfo> {
+        let mut info = input_absinfo::from(AbsInfo::default());
+        check(unsafe { ev_get_abs(self.as_raw_fd(), code as _, &mut info) })?;
+        Ok(info.into())
+    }
+
+    /// Change the range, fuzz, flat and resolution of an absolute axis
+    /// (`EVIOCSABS`).
+    pub fn set_absinfo(&self, code: u16, info: AbsInfo) -> io::Result<()> {
+        let info = input_absinfo::from(info);
+        // input-linux-sys builds this request with the read direction and the
+        // EVIOCGABS number, which the kernel rejects.
+        let request = request_code_write!(b'E', 0xc0 + code as u32, size_of::<input_absinfo>());
+        check(unsafe { convert_ioctl_res!(ioctl(self.as_raw_fd(), request as _, &info)) })?;
+        Ok(())
+    }
+}
+
+impl AsRawFd for Device {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+/// Error of reading a device that has been unplugged.
+pub(crate) fn is_disconnect(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(nix::libc::ENODEV) || err.kind() == io::ErrorKind::UnexpectedEof
+}
+
+pub(crate) fn write_event(mut file: &File, type_: u16, code: u16, value: i32) -> io::Result<()> {
+    let event = input_event {
+        time: timeval { tv_sec: 0, tv_usec: 0 },
+        type_,
+        code,
+        value,
+    };
+    // Safety: input_event is plain old data.
+    let buf = unsafe {
+        std::slice::from_raw_parts(&event as *const input_event as *const u8, size_of::<input_event>())
+    };
+    file.write_all(buf)
+}
+
+/// Number of codes of an event type, type `0` counting event types.
+pub(crate) fn code_count(type_: u16) -> usize {
+    (match type_ as _ {
+        EV_SYN => EV_CNT,
+        EV_KEY => KEY_CNT,
+        EV_REL => REL_CNT,
+        EV_ABS => ABS_CNT,
+        EV_MSC => MSC_CNT,
+        EV_SW => SW_CNT,
+        EV_LED => LED_CNT,
+        EV_SND => SND_CNT,
+        EV_REP => REP_CNT,
+        EV_FF => FF_CNT as _,
+        _ => 0,
+    }) as usize
+}
+
+pub(crate) fn bitmask_len(bits: usize) -> usize {
+    bits.div_ceil(8)
+}
+
+pub(crate) fn bit_is_set(mask: &[u8], bit: u16) -> bool {
+    mask.get(bit as usize / 8).is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+}
+
+pub(crate) fn c_string(buf: &[c_char]) -> String {
+    let bytes: Vec<u8> = buf.iter().take_while(|c| **c != 0).map(|c| *c as u8).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Strings the driver didn't set fail with `ENOENT`.
+fn optional_string(res: Result<i32>, buf: &[c_char]) -> io::Result<Option<String>> {
+    match res {
+        Err(nix::errno::Errno::ENOENT) => Ok(None),
+        res => check(res).map(|_| Some(c_string(buf)).filter(|s| !s.is_empty())),
+    }
+}
+
+pub(crate) fn check(res: Result<i32>) -> io::Result<i32> {
+    res.map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn props_from_bitmask() {
+        assert!(InputProps::from_bitmask(&[]).is_empty());
+        let props = InputProps::from_bitmask(&[0b0100_0101]);
+        assert_eq!(props.iter().collect::<Vec<_>>(), [InputProp::Pointer, InputProp::ButtonPad, InputProp::Accelerometer]);
+        assert_eq!(props, [InputProp::Accelerometer, InputProp::ButtonPad, InputProp::Pointer].into_iter().collect());
+
+        // Unknown properties are kept but not listed.
+        let props = InputProps::from_bitmask(&[0b0000_0010, 0b0000_0001, 0, 0b1000_0000]);
+        assert_eq!(props.bits(), 1 << 31 | 1 << 8 | 1 << 1);
+        assert_eq!(props.iter().collect::<Vec<_>>(), [InputProp::Direct]);
+        assert!(props.contains(InputProp::Direct) && !props.contains(InputProp::Pointer));
+    }
+
+    #[test]
+    fn props_insert_remove() {
+        let mut props = InputProps::empty().with(InputProp::SemiMt);
+        props.insert(InputProp::TopButtonPad);
+        props.remove(InputProp::SemiMt);
+        assert_eq!(props.iter().collect::<Vec<_>>(), [InputProp::TopButtonPad]);
+        assert_eq!(props.bits(), 1 << INPUT_PROP_TOPBUTTONPAD);
+    }
+}
@@ -0,0 +1,409 @@
+//! Conversions between analog axes and digital buttons.
+
+use crate::axis::Stick;
+use crate::pipeline::Stage;
+use crate::{Axis, Button, Direction, Event, Hat, HatState};
+
+/// Turns an axis into press / release events of a button.
+///
+/// The button is pressed once the axis reaches `press` and released once it
+/// falls back to `release`; the gap between the two is the hysteresis that
+/// keeps a noisy axis from chattering.  If `press` is below `release` the
+/// button triggers on the negative side of the axis instead.
+#[derive(Debug, Clone)]
+pub struct AxisButton {
+    axis: Axis,
+    button: Button,
+    press: f64,
+    release: f64,
+    pressed: bool,
+    consume: bool,
+}
+
+impl AxisButton {
+    /// `AxisButton::new(Axis::TriggerL, Button::BumperL, 128, 96)`
+    pub fn new(axis: Axis, button: Button, press: i32, release: i32) -> Self {
+        Self {
+            axis,
+            button,
+            press: press.into(),
+            release: release.into(),
+            pressed: false,
+            consume: false,
+        }
+    }
+
+    /// Drop the axis events instead of passing them on.
+    pub fn consume(mut self) -> Self {
+        self.consume = true;
+        self
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    fn update(&mut self, value: f64) -> Option<bool> {
+        let pressed = if self.press >= self.release {
+            if self.pressed { value > self.release } else { value >= self.press }
+        } else if self.pressed {
+            value < self.release
+        } else {
+            value <= self.press
+        };
+        (pressed != self.pressed).then(|| {
+            self.pressed = pressed;
+            pressed
+        })
+    }
+}
+
+impl<T> Stage<T> for AxisButton where T: TryFrom<i32> + Copy + Into<f64> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let Event::Axis(axis, value) = event else {
+            out.push(event);
+            return;
+        };
+        if axis != self.axis {
+            out.push(event);
+            return;
+        }
+        let change = self.update(value.into());
+        if !self.consume {
+            out.push(event);
+        }
+        if let Some(pressed) = change {
+            out.push(Event::Button(self.button, pressed));
+        }
+    }
+}
+
+/// Number of directions a stick is split into.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sectors {
+    /// Up, Down, Left and Right, 90° each.
+    Four,
+    /// The four directions plus diagonals, which hold two directions at
+    /// once.  `diagonal` is the width in degrees of each diagonal sector,
+    /// `45.0` splitting the circle evenly.
+    Eight { diagonal: f32 },
+}
+
+/// Turns a stick into D-Pad `Up` / `Down` / `Left` / `Right` events.
+#[derive(Debug, Clone)]
+pub struct StickDpad {
+    stick: Stick,
+    sectors: Sectors,
+    center: (f64, f64),
+    half_range: f64,
+    deadzone: f64,
+    position: (f64, f64),
+    held: [bool; 4],
+    consume: bool,
+}
+
+const UP: usize = 0;
+const DOWN: usize = 1;
+const LEFT: usize = 2;
+const RIGHT: usize = 3;
+
+impl StickDpad {
+    /// `deadzone` is the fraction of the full deflection below which no
+    /// direction is held.
+    pub fn new(stick: Stick, minimum: i32, maximum: i32, sectors: Sectors, deadzone: f32) -> Self {
+        let center = (minimum as f64 + maximum as f64) / 2.0;
+        Self {
+            stick,
+            sectors,
+            center: (center, center),
+            half_range: ((maximum as f64 - minimum as f64) / 2.0).max(f64::EPSILON),
+            deadzone: deadzone.into(),
+            position: (center, center),
+            held: [false; 4],
+            consume: false,
+        }
+    }
+
+    /// Drop the stick events instead of passing them on.
+    pub fn consume(mut self) -> Self {
+        self.consume = true;
+        self
+    }
+
+    fn directions(&self) -> [bool; 4] {
+        let x = (self.position.0 - self.center.0) / self.half_range;
+        // evdev Y grows downwards
+        let y = (self.center.1 - self.position.1) / self.half_range;
+        if x.hypot(y) < self.deadzone.max(f64::EPSILON) {
+            return [false; 4];
+        }
+        // Angle from the right, counter-clockwise, in 0..360
+        let angle = y.atan2(x).to_degrees().rem_euclid(360.0);
+        // A direction is held within this angle of it, which for eight
+        // sectors includes the neighbouring diagonal sectors.
+        let reach = match self.sectors {
+            Sectors::Four => 45.0,
+            Sectors::Eight { diagonal } => 45.0 + (diagonal as f64).clamp(0.0, 90.0) / 2.0,
+        };
+        let within = |direction: f64| {
+            let delta = ((angle - direction + 180.0).rem_euclid(360.0) - 180.0).abs();
+            // Exact sector boundaries go to the horizontal direction.
+            delta < reach || (delta == reach && direction % 180.0 == 0.0)
+        };
+        [within(90.0), within(270.0), within(180.0), within(0.0)]
+    }
+}
+
+impl<T> Stage<T> for StickDpad where T: TryFrom<i32> + Copy + Into<f64> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let Some((stick, is_y, value)) = Stick::of(&event) else {
+            out.push(event);
+            return;
+        };
+        if stick != self.stick {
+            out.push(event);
+            return;
+        }
+        if is_y {
+            self.position.1 = (*value).into();
+        } else {
+            self.position.0 = (*value).into();
+        }
+        if !self.consume {
+            out.push(event);
+        }
+        let held = self.directions();
+        let buttons = [Button::Up, Button::Down, Button::Left, Button::Right];
+        // Releases first, so a sweep never holds opposite directions.
+        for pressed in [false, true] {
+            for direction in [UP, DOWN, LEFT, RIGHT] {
+                if held[direction] != self.held[direction] && held[direction] == pressed {
+                    out.push(Event::Button(buttons[direction], pressed));
+                }
+            }
+        }
+        self.held = held;
+    }
+}
+
+/// Turns the directions of a hat into D-Pad `Up` / `Down` / `Left` /
+/// `Right` events, e.g. so they can be repeated or bound like D-Pad buttons.
+#[derive(Debug, Clone)]
+pub struct HatDpad {
+    hat: Hat,
+    state: HatState,
+    consume: bool,
+}
+
+impl HatDpad {
+    pub fn new(hat: Hat) -> Self {
+        Self { hat, state: HatState::new(), consume: false }
+    }
+
+    /// Drop the hat events instead of passing them on.
+    pub fn consume(mut self) -> Self {
+        self.consume = true;
+        self
+    }
+}
+
+impl<T> Stage<T> for HatDpad where T: TryFrom<i32> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let Event::Hat(hat, direction) = event else {
+            out.push(event);
+            return;
+        };
+        if hat != self.hat {
+            out.push(event);
+            return;
+        }
+        if !self.consume {
+            out.push(event);
+        }
+        for (direction, pressed) in self.state.set(hat, direction) {
+            let button = match direction {
+                Direction::Up => Button::Up,
+                Direction::Down => Button::Down,
+                Direction::Left => Button::Left,
+                _ => Button::Right,
+            };
+            out.push(Event::Button(button, pressed));
+        }
+    }
+}
+
+/// Synthesizes an analog axis value from a digital button, e.g. a
+/// `TriggerL` value from `BumperL` on pads without analog triggers.
+#[derive(Debug, Clone)]
+pub struct ButtonAxis {
+    button: Button,
+    axis: Axis,
+    pressed: i32,
+    released: i32,
+    consume: bool,
+}
+
+impl ButtonAxis {
+    /// `ButtonAxis::new(Button::BumperL, Axis::TriggerL, 255, 0)`
+    pub fn new(button: Button, axis: Axis, pressed: i32, released: i32) -> Self {
+        Self { button, axis, pressed, released, consume: false }
+    }
+
+    /// Drop the button events instead of passing them on.
+    pub fn consume(mut self) -> Self {
+        self.consume = true;
+        self
+    }
+}
+
+impl<T> Stage<T> for ButtonAxis where T: TryFrom<i32> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let Event::Button(button, pushed) = event else {
+            out.push(event);
+            return;
+        };
+        if button != self.button {
+            out.push(event);
+            return;
+        }
+        if !self.consume {
+            out.push(event);
+        }
+        let value = if pushed { self.pressed } else { self.released };
+        if let Ok(value) = T::try_from(value) {
+            out.push(Event::Axis(self.axis, value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run<S: Stage<i32>>(stage: &mut S, events: impl IntoIterator<Item = Event>) -> Vec<Event> {
+        let mut out = Vec::new();
+        for event in events {
+            stage.process(event, &mut out);
+        }
+        out
+    }
+
+    fn trigger(value: i32) -> Event {
+        Event::Axis(Axis::TriggerL, value)
+    }
+
+    fn bumper(pushed: bool) -> Event {
+        Event::Button(Button::BumperL, pushed)
+    }
+
+    #[test]
+    fn axis_button_thresholds() {
+        let mut stage = AxisButton::new(Axis::TriggerL, Button::BumperL, 128, 96).consume();
+        assert_eq!(run(&mut stage, [trigger(127)]), []);
+        assert_eq!(run(&mut stage, [trigger(128)]), [bumper(true)]);
+        assert!(stage.is_pressed());
+        assert_eq!(run(&mut stage, [trigger(97)]), []);
+        assert_eq!(run(&mut stage, [trigger(96)]), [bumper(false)]);
+    }
+
+    #[test]
+    fn axis_button_no_chatter() {
+        let mut stage = AxisButton::new(Axis::TriggerL, Button::BumperL, 128, 96).consume();
+        let noise = [128, 127, 129, 128, 100, 97, 127, 96, 97, 95, 127, 96];
+        assert_eq!(run(&mut stage, noise.map(trigger)), [bumper(true), bumper(false)]);
+    }
+
+    #[test]
+    fn axis_button_negative_side() {
+        let mut stage = AxisButton::new(Axis::JoyY, Button::Up, -100, -50);
+        let events = run(&mut stage, [-99, -100, -51, -50].map(|value| Event::Axis(Axis::JoyY, value)));
+        assert_eq!(events, [
+            Event::Axis(Axis::JoyY, -99),
+            Event::Axis(Axis::JoyY, -100),
+            Event::Button(Button::Up, true),
+            Event::Axis(Axis::JoyY, -51),
+            Event::Axis(Axis::JoyY, -50),
+            Event::Button(Button::Up, false),
+        ]);
+    }
+
+    #[test]
+    fn axis_button_passes_other_events() {
+        let mut stage = AxisButton::new(Axis::TriggerL, Button::BumperL, 128, 96).consume();
+        let other = [Event::Axis(Axis::TriggerR, 255), bumper(true)];
+        assert_eq!(run(&mut stage, other), other);
+    }
+
+    fn held(stage: &mut StickDpad, x: i32, y: i32) -> [bool; 4] {
+        run(stage, [Event::Axis(Axis::JoyX, x), Event::Axis(Axis::JoyY, y)]);
+        stage.held
+    }
+
+    #[test]
+    fn stick_dpad_four_sectors() {
+        let mut stage = StickDpad::new(Stick::Joy, -100, 100, Sectors::Four, 0.2).consume();
+        assert_eq!(held(&mut stage, 10, 10), [false; 4]);
+        assert_eq!(held(&mut stage, 0, -100), [true, false, false, false]);
+        assert_eq!(held(&mut stage, 100, 0), [false, false, false, true]);
+        // Exactly on the diagonal goes to the horizontal direction.
+        assert_eq!(held(&mut stage, 50, -50), [false, false, false, true]);
+        assert_eq!(held(&mut stage, -50, 50), [false, false, true, false]);
+        assert_eq!(held(&mut stage, 49, -50), [true, false, false, false]);
+    }
+
+    #[test]
+    fn stick_dpad_eight_sectors() {
+        let mut stage = StickDpad::new(Stick::Joy, -100, 100, Sectors::Eight { diagonal: 45.0 }, 0.2).consume();
+        assert_eq!(held(&mut stage, 50, -50), [true, false, false, true]);
+        // The right sector ends 22.5° up, at y = 92 * tan(22.5°) = 38.1.
+        assert_eq!(held(&mut stage, 92, -38), [false, false, false, true]);
+        assert_eq!(held(&mut stage, 92, -39), [true, false, false, true]);
+        assert_eq!(held(&mut stage, -100, 0), [false, false, true, false]);
+    }
+
+    #[test]
+    fn stick_dpad_releases_first() {
+        let mut stage = StickDpad::new(Stick::Joy, -100, 100, Sectors::Four, 0.2).consume();
+        let sweep = [Event::Axis(Axis::JoyY, -100), Event::Axis(Axis::JoyX, 100), Event::Axis(Axis::JoyY, 0)];
+        let events = run(&mut stage, sweep);
+        assert_eq!(events, [
+            Event::Button(Button::Up, true),
+            Event::Button(Button::Up, false),
+            Event::Button(Button::Right, true),
+        ]);
+    }
+
+    #[test]
+    fn hat_dpad() {
+        let mut stage = HatDpad::new(Hat::Pov).consume();
+        let events = run(&mut stage, [
+            Event::Hat(Hat::Pov, Direction::UpRight),
+            Event::Hat(Hat::Trim, Direction::Up),
+            Event::Hat(Hat::Pov, Direction::Left),
+        ]);
+        assert_eq!(events, [
+            Event::Button(Button::Right, true),
+            Event::Button(Button::Up, true),
+            Event::Hat(Hat::Trim, Direction::Up),
+            Event::Button(Button::Right, false),
+            Event::Button(Button::Up, false),
+            Event::Button(Button::Left, true),
+        ]);
+    }
+
+    #[test]
+    fn button_axis() {
+        let mut stage = ButtonAxis::new(Button::BumperL, Axis::TriggerL, 255, 0);
+        let events = run(&mut stage, [bumper(true), Event::Button(Button::BumperR, true), bumper(false)]);
+        assert_eq!(events, [
+            bumper(true),
+            trigger(255),
+            Event::Button(Button::BumperR, true),
+            bumper(false),
+            trigger(0),
+        ]);
+        let mut stage = ButtonAxis::new(Button::BumperL, Axis::TriggerL, 255, 0).consume();
+        assert_eq!(run(&mut stage, [bumper(true)]), [trigger(255)]);
+    }
+}
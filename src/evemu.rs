@@ -0,0 +1,445 @@
+//! Reader and writer for the text format of `evemu-record` / `evemu-play`.
+//!
+//! A recording starts with a device description:
+//!
+//! ```text
+//! # EVEMU 1.3
+//! N: Microsoft X-Box 360 pad
+//! I: 0003 045e 028e 0114
+//! P: 00 00 00 00 00 00 00 00
+//! B: 00 0b 00 00 00 00 00 00 00
+//! A: 00 -32768 32767 16 128 0
+//! ```
+//!
+//! followed by one `E: sec.usec type code value` line per event.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use input_linux_sys::{input_event, timeval, EV_ABS};
+
+use crate::device::{bit_is_set, bitmask_len, code_count};
+use crate::time::Timestamp;
+use crate::{AbsInfo, Device, Event, HatState, InputId};
+
+/// Everything evemu needs to recreate a device: identity, supported event
+/// codes, properties and absolute axis ranges.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceDescription {
+    pub name: String,
+    pub id: InputId,
+    /// `INPUT_PROP_*` bitmask
+    pub props: Vec<u8>,
+    /// Bitmask of supported codes per event type, type `0` holding the
+    /// supported event types.
+    pub bits: BTreeMap<u16, Vec<u8>>,
+    pub abs: BTreeMap<u16, AbsInfo>,
+}
+
+impl DeviceDescription {
+    /// Query the description of an open device.
+    pub fn from_device(device: &Device) -> io::Result<Self> {
+        let types = device.bits(0)?;
+        let mut bits = BTreeMap::new();
+        let mut abs = BTreeMap::new();
+        bits.insert(0, types.clone());
+        for type_ in 1..code_count(0) as u16 {
+            if code_count(type_) == 0 || !bit_is_set(&types, type_) {
+                continue;
+            }
+            let mask = device.bits(type_)?;
+            if type_ == EV_ABS as u16 {
+                for code in 0..code_count(type_) as u16 {
+                    if bit_is_set(&mask, code) {
+                        abs.insert(code, device.absinfo(code)?);
+                    }
+                }
+            }
+            bits.insert(type_, mask);
+        }
+        Ok(Self {
+            name: device.name()?,
+            id: device.id()?,
+            props: device.props_raw()?,
+            bits,
+            abs,
+        })
+    }
+
+    /// Whether the described device supports the given event type and code.
+    pub fn has(&self, type_: u16, code: u16) -> bool {
+        self.bits.get(&type_).is_some_and(|mask| bit_is_set(mask, code))
+    }
+
+    /// Write the description header.
+    pub fn write_to(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "# EVEMU 1.3")?;
+        writeln!(out, "N: {}", self.name)?;
+        writeln!(out, "I: {:04x} {:04x} {:04x} {:04x}",
+            self.id.bustype, self.id.vendor, self.id.product, self.id.version)?;
+        for chunk in padded(&self.props, bitmask_len(32)).chunks(8) {
+            write!(out, "P:")?;
+            for byte in chunk {
+                write!(out, " {byte:02x}")?;
+            }
+            writeln!(out)?;
+        }
+        for (type_, mask) in &self.bits {
+            for chunk in padded(mask, bitmask_len(code_count(*type_))).chunks(8) {
+                write!(out, "B: {type_:02x}")?;
+                for byte in chunk {
+                    write!(out, " {byte:02x}")?;
+                }
+                writeln!(out)?;
+            }
+        }
+        for (code, info) in &self.abs {
+            writeln!(out, "A: {:02x} {} {} {} {} {}",
+                code, info.minimum, info.maximum, info.fuzz, info.flat, info.resolution)?;
+        }
+        Ok(())
+    }
+
+    /// Apply one header line, returning `false` if it is not a header line.
+    fn parse_line(&mut self, line: &str) -> io::Result<bool> {
+        let Some((tag, rest)) = line.split_once(':') else {
+            return Ok(false);
+        };
+        let mut fields = rest.split_whitespace();
+        match tag {
+            "N" => self.name = rest.trim().to_owned(),
+            "I" => {
+                let mut next = || hex(fields.next());
+                self.id = InputId { bustype: next()?, vendor: next()?, product: next()?, version: next()? };
+            }
+            "P" => {
+                for byte in fields {
+                    self.props.push(hex(Some(byte))?);
+                }
+            }
+            "B" => {
+                let type_ = hex(fields.next())?;
+                let mask = self.bits.entry(type_).or_default();
+                for byte in fields {
+                    mask.push(hex(Some(byte))?);
+                }
+            }
+            "A" => {
+                let code = hex(fields.next())?;
+                let mut next = || decimal(fields.next());
+                let (minimum, maximum, fuzz, flat) = (next()?, next()?, next()?, next()?);
+                // Resolution was added in evemu 1.1
+                let resolution = fields.next().map(|field| decimal(Some(field))).transpose()?.unwrap_or(0);
+                self.abs.insert(code, AbsInfo { value: 0, minimum, maximum, fuzz, flat, resolution });
+            }
+            // Initial LED / switch state and other additions of newer
+            // versions, not needed here
+            _ if tag.len() == 1 => {}
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+fn padded(mask: &[u8], len: usize) -> Vec<u8> {
+    let mut out = mask.to_vec();
+    out.resize(len.max(mask.len()).next_multiple_of(8), 0);
+    out
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn hex<N: TryFrom<u32>>(field: Option<&str>) -> io::Result<N> {
+    let field = field.ok_or_else(|| invalid("missing field"))?;
+    u32::from_str_radix(field, 16).ok()
+        .and_then(|value| N::try_from(value).ok())
+        .ok_or_else(|| invalid(format!("invalid hex field: {field}")))
+}
+
+fn decimal(field: Option<&str>) -> io::Result<i32> {
+    let field = field.ok_or_else(|| invalid("missing field"))?;
+    field.parse().map_err(|_| invalid(format!("invalid field: {field}")))
+}
+
+fn parse_event(line: &str) -> io::Result<input_event> {
+    let line = line.split('#').next().unwrap_or_default();
+    let mut fields = line.split_whitespace();
+    let time = fields.next().ok_or_else(|| invalid("missing time"))?;
+    let (sec, usec) = time.split_once('.').unwrap_or((time, "0"));
+    let time = sec.parse().ok().zip(usec.parse().ok())
+        .map(|(tv_sec, tv_usec)| timeval { tv_sec, tv_usec })
+        .filter(|time| Timestamp::from_timeval(time).is_some())
+        .ok_or_else(|| invalid(format!("invalid time: {time}")))?;
+    Ok(input_event {
+        time,
+        type_: hex(fields.next())?,
+        code: hex(fields.next())?,
+        value: decimal(fields.next())?,
+    })
+}
+
+/// Streaming reader of an evemu recording.  The description is parsed up
+/// front, the events are yielded by iterating.
+pub struct Reader<R> {
+    input: R,
+    description: DeviceDescription,
+    pending: Option<String>,
+}
+
+impl<R: BufRead> Reader<R> {
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut description = DeviceDescription::default();
+        let mut line = String::new();
+        let pending = loop {
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                break None;
+            }
+            let trimmed = line.trim_end_matches(['\n', '\r']);
+            if trimmed.starts_with("E:") {
+                break Some(trimmed.to_owned());
+            }
+            if trimmed.trim().is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if !description.parse_line(trimmed)? {
+                return Err(invalid(format!("unexpected line: {trimmed}")));
+            }
+        };
+        Ok(Self { input, description, pending })
+    }
+
+    pub fn description(&self) -> &DeviceDescription {
+        &self.description
+    }
+
+    pub fn into_description(self) -> DeviceDescription {
+        self.description
+    }
+
+    fn next_event(&mut self) -> io::Result<Option<input_event>> {
+        if let Some(line) = self.pending.take() {
+            return parse_event(&line[2..]).map(Some);
+        }
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            let trimmed = line.trim();
+            if let Some(event) = trimmed.strip_prefix("E:") {
+                return parse_event(event).map(Some);
+            }
+            if !trimmed.is_empty() && !trimmed.starts_with('#') {
+                return Err(invalid(format!("unexpected line: {trimmed}")));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Reader<R> {
+    type Item = io::Result<input_event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl<R> fmt::Debug for Reader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reader").field("description", &self.description).finish_non_exhaustive()
+    }
+}
+
+/// Writer of an evemu recording.  Event times are written relative to the
+/// first event, like `evemu-record` does.
+pub struct Writer<W: Write> {
+    output: W,
+    start: Option<Timestamp>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Start a recording by writing the description header.
+    pub fn new(mut output: W, description: &DeviceDescription) -> io::Result<Self> {
+        description.write_to(&mut output)?;
+        Ok(Self { output, start: None })
+    }
+
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the event has an
+    /// invalid time.
+    pub fn write_event(&mut self, event: &input_event) -> io::Result<()> {
+        let time = Timestamp::of(event)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid event time"))?;
+        let start = *self.start.get_or_insert(time);
+        let offset = time.duration_since(start);
+        writeln!(self.output, "E: {}.{:06} {:04x} {:04x} {:04}",
+            offset.as_secs(), offset.subsec_micros(), event.type_, event.code, event.value)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+}
+
+impl<W: Write> fmt::Debug for Writer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Writer").field("start", &self.start).finish_non_exhaustive()
+    }
+}
+
+/// A complete recording held in memory.
+#[derive(Clone)]
+pub struct Recording {
+    pub description: DeviceDescription,
+    pub events: Vec<input_event>,
+}
+
+impl Recording {
+    pub fn read(input: impl BufRead) -> io::Result<Self> {
+        let mut reader = Reader::new(input)?;
+        let events = reader.by_ref().collect::<io::Result<_>>()?;
+        Ok(Self { description: reader.into_description(), events })
+    }
+
+    pub fn write(&self, output: impl Write) -> io::Result<()> {
+        let mut writer = Writer::new(output, &self.description)?;
+        for event in &self.events {
+            writer.write_event(event)?;
+        }
+        writer.flush()
+    }
+
+    /// Decode the recorded events with [`Event::from_input_event`], skipping
+    /// those it has no variant for, like keyboard keys or the `REL_WHEEL` /
+    /// `REL_HWHEEL` scroll wheels, and those with an invalid time.  They are
+    /// still in [`Recording::events`].
+    pub fn decoded<T>(&self) -> impl Iterator<Item = (Timestamp, Event<T>)> + '_ where T: TryFrom<i32> {
+        let mut state = HatState::new();
+        self.events.iter().filter_map(move |event| {
+            Some((Timestamp::of(event)?, Event::from_input_event(event, &mut state)?))
+        })
+    }
+}
+
+impl fmt::Debug for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recording")
+            .field("description", &self.description)
+            .field("events", &self.events.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Axis, Button, Direction, Hat};
+
+    const KEYBOARD: &str = include_str!("../testdata/k400-plus.evemu");
+    const GAMEPAD: &str = include_str!("../testdata/xbox360-pad.evemu");
+
+    /// What [`Recording::write`] gives back: the sample without comments,
+    /// except the version line.
+    fn canonical(sample: &str) -> String {
+        sample.lines()
+            .filter(|line| !line.starts_with('#') || line.starts_with("# EVEMU"))
+            .map(|line| line.split('\t').next().unwrap_or_default().to_owned() + "\n")
+            .collect()
+    }
+
+    fn round_trip(sample: &str) -> Recording {
+        let recording = Recording::read(sample.as_bytes()).unwrap();
+        let mut written = Vec::new();
+        recording.write(&mut written).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), canonical(sample));
+        recording
+    }
+
+    #[test]
+    fn keyboard_header() {
+        let recording = round_trip(KEYBOARD);
+        let description = &recording.description;
+        assert_eq!(description.name, "Logitech K400 Plus");
+        assert_eq!(description.id, InputId { bustype: 0x03, vendor: 0x046d, product: 0x404d, version: 0x0111 });
+        assert_eq!(description.props, [0; 8]);
+        assert!(description.has(0x01, 0x1e));
+        assert!(description.has(0x02, 0x08));
+        assert!(!description.has(0x03, 0x00));
+        assert!(description.abs.is_empty());
+        assert_eq!(recording.events.len(), 13);
+    }
+
+    #[test]
+    fn gamepad_header() {
+        let recording = round_trip(GAMEPAD);
+        let description = &recording.description;
+        assert_eq!(description.abs.len(), 8);
+        let x = AbsInfo { value: 0, minimum: -32768, maximum: 32767, fuzz: 16, flat: 128, resolution: 0 };
+        assert_eq!(description.abs[&0x00], x);
+        assert_eq!(description.abs[&0x11].minimum, -1);
+        assert!(description.has(0x01, 0x130));
+        assert!(description.has(0x15, 0x50));
+    }
+
+    #[test]
+    fn event_lines() {
+        let recording = Recording::read(KEYBOARD.as_bytes()).unwrap();
+        let event = &recording.events[6];
+        assert_eq!((event.type_, event.code, event.value), (0x02, 0x08, -1));
+        assert_eq!(Timestamp::of(event).unwrap().as_duration(), std::time::Duration::from_micros(16024));
+        assert_eq!(recording.events[3].value, 458756);
+    }
+
+    #[test]
+    fn decode_skips_unmapped_events() {
+        let recording = Recording::read(KEYBOARD.as_bytes()).unwrap();
+        let decoded: Vec<_> = recording.decoded::<i32>().map(|(_, event)| event).collect();
+        assert_eq!(decoded, [Event::Axis(Axis::MouseX, 3), Event::Axis(Axis::MouseY, -2)]);
+
+        let recording = Recording::read(GAMEPAD.as_bytes()).unwrap();
+        let decoded: Vec<_> = recording.decoded::<i32>().map(|(_, event)| event).collect();
+        assert_eq!(decoded, [
+            Event::Axis(Axis::JoyX, -4096),
+            Event::Hat(Hat::Pov, Direction::Up),
+            Event::Button(Button::ActionA, true),
+            Event::Hat(Hat::Pov, Direction::Center),
+        ]);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(Recording::read("N: pad\ngarbage\n".as_bytes()).is_err());
+        assert!(Recording::read("N: pad\nE: 0.000000 0001 zz 1\n".as_bytes()).is_err());
+        for time in ["-1.000000", "1.1000000", "9223372036854775808.000000", "1.-1"] {
+            let line = format!("N: pad\nE: {time} 0001 0130 1\n");
+            assert!(Recording::read(line.as_bytes()).is_err(), "{time}");
+        }
+    }
+
+    #[test]
+    fn wheel_events_are_not_decoded() {
+        let recording = Recording::read(KEYBOARD.as_bytes()).unwrap();
+        let wheel = recording.events.iter().find(|event| (event.type_, event.code) == (0x02, 0x08)).unwrap();
+        assert_eq!(wheel.value, -1);
+        assert_eq!(Event::<i32>::from_input_event(wheel, &mut HatState::new()), None);
+        assert!(recording.decoded::<i32>().all(|(_, event)| event.value() != Some(&-1)));
+    }
+
+    #[test]
+    fn invalid_times() {
+        let mut recording = Recording::read(KEYBOARD.as_bytes()).unwrap();
+        recording.events[0].time.tv_usec = 1_000_000;
+        assert_eq!(recording.decoded::<i32>().count(), 1);
+        let error = recording.write(Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,581 @@
+//! The flat event shape: one variant per control, with each hat reported
+//! as four direction buttons.
+//!
+//! [`crate::Event`] had this shape before it was split into buttons, axes
+//! and hats.  It is kept for code and recordings written against it;
+//! [`Converter`] translates between the two shapes.
+
+use std::cmp::Ordering;
+
+use input_linux_sys::{input_event, ABS_HAT0X, ABS_HAT3Y, EV_ABS};
+
+use crate::{Axis, Button, Direction, Hat, HatState};
+
+/// An event in the flat shape.
+///
+/// Each button and axis variant stands for the [`Button`] or [`Axis`] of
+/// the same name.  `PovUp` .. `PovRight` are the directions of
+/// [`Hat::Pov`], and likewise `HatUp` .. `HatRight` of [`Hat::Extra`],
+/// `TrimUp` .. `TrimRight` of [`Hat::Trim`] and `MicUp` .. `MicRight` of
+/// [`Hat::Mic`].  The remaining variants are those of [`crate::Event`].
+///
+/// With the `serde` feature enabled flat events keep the format they always
+/// had: `"Disconnect"`, `{"ActionA":true}`, `{"JoyX":-512}`,
+/// `{"Number":[3,true]}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Event<T = i32> where T: TryFrom<i32> {
+    Disconnect,
+    Exit(bool),
+    ActionA(bool),
+    ActionB(bool),
+    ActionC(bool),
+    ActionH(bool),
+    ActionV(bool),
+    ActionD(bool),
+    MenuL(bool),
+    MenuR(bool),
+    Joy(bool),
+    Cam(bool),
+    BumperL(bool),
+    BumperR(bool),
+    TriggerL(T),
+    TriggerR(T),
+    Up(bool),
+    Down(bool),
+    Left(bool),
+    Right(bool),
+    PovUp(bool),
+    PovDown(bool),
+    PovLeft(bool),
+    PovRight(bool),
+    HatUp(bool),
+    HatDown(bool),
+    HatLeft(bool),
+    HatRight(bool),
+    TrimUp(bool),
+    TrimDown(bool),
+    TrimLeft(bool),
+    TrimRight(bool),
+    MicUp(bool),
+    MicDown(bool),
+    MicLeft(bool),
+    MicRight(bool),
+    JoyX(T),
+    JoyY(T),
+    JoyZ(T),
+    CamX(T),
+    CamY(T),
+    CamZ(T),
+    Slew(T),
+    Throttle(T),
+    ThrottleL(T),
+    ThrottleR(T),
+    Volume(T),
+    Wheel(T),
+    Rudder(T),
+    Gas(T),
+    Brake(T),
+    MicPush(bool),
+    Trigger(bool),
+    Bumper(bool),
+    ActionM(bool),
+    ActionL(bool),
+    ActionR(bool),
+    Pinky(bool),
+    PinkyForward(bool),
+    PinkyBackward(bool),
+    FlapsUp(bool),
+    FlapsDown(bool),
+    BoatForward(bool),
+    BoatBackward(bool),
+    AutopilotPath(bool),
+    AutopilotAlt(bool),
+    EngineMotorL(bool),
+    EngineMotorR(bool),
+    EngineFuelFlowL(bool),
+    EngineFuelFlowR(bool),
+    EngineIgnitionL(bool),
+    EngineIgnitionR(bool),
+    SpeedbrakeBackward(bool),
+    SpeedbrakeForward(bool),
+    ChinaBackward(bool),
+    ChinaForward(bool),
+    Apu(bool),
+    RadarAltimeter(bool),
+    LandingGearSilence(bool),
+    Eac(bool),
+    AutopilotToggle(bool),
+    ThrottleButton(bool),
+    MouseX(T),
+    MouseY(T),
+    Mouse(bool),
+    Number(i8, bool),
+    PaddleLeft(bool),
+    PaddleRight(bool),
+    PinkyLeft(bool),
+    PinkyRight(bool),
+    Context(bool),
+    Dpi(bool),
+    ScrollX(T),
+    ScrollY(T),
+    Scroll(bool),
+    ActionWheelX(T),
+    ActionWheelY(T),
+    Repeat(u16),
+    RepeatDelay(T),
+    RepeatPeriod(T),
+    Power(u16, T),
+    Click(bool),
+    Bell(bool),
+    Tone(T),
+}
+
+impl<T> Event<T> where T: TryFrom<i32> {
+    /// Decode a raw event.  `state` tracks which hat directions are held,
+    /// to know which one a centered hat axis releases.
+    pub fn from_input_event(input_event: &input_event, state: &mut u8) -> Option<Self> {
+        let code = input_event.code as i32;
+        if input_event.type_ as i32 == EV_ABS && (ABS_HAT0X..=ABS_HAT3Y).contains(&code) {
+            return Self::from_hat_event(input_event, state);
+        }
+        // Everything but hats decodes without state.
+        Some(match crate::Event::from_input_event(input_event, &mut HatState::new())? {
+            crate::Event::Button(button, pushed) => button_event(button, pushed),
+            crate::Event::Axis(axis, value) => axis_event(axis, value),
+            event => Converter::new().flatten(event).pop()?,
+        })
+    }
+
+    fn from_hat_event(input_event: &input_event, state: &mut u8) -> Option<Self> {
+        let index = (input_event.code as i32 - ABS_HAT0X) as u8;
+        let hat = Hat::ALL[index as usize / 2];
+        let (less, greater) = if index.is_multiple_of(2) {
+            (Direction::Left, Direction::Right)
+        } else {
+            (Direction::Up, Direction::Down)
+        };
+        let mask = 1 << index;
+        let (direction, held) = match input_event.value.cmp(&0) {
+            Ordering::Greater => {
+                *state |= mask;
+                (greater, true)
+            }
+            Ordering::Less => (less, true),
+            Ordering::Equal => {
+                let was_greater = *state & mask > 0;
+                *state &= !mask;
+                (if was_greater { greater } else { less }, false)
+            }
+        };
+        hat_event(hat, direction, held)
+    }
+
+    /// The layered event of a flat one, or the hat, direction and held
+    /// state of a hat direction event.
+    fn split(self) -> Result<crate::Event<T>, (Hat, Direction, bool)> {
+        use crate::Event as L;
+        Ok(match self {
+            Event::Disconnect => L::Disconnect,
+            Event::Exit(pushed) => L::Button(Button::Exit, pushed),
+            Event::ActionA(pushed) => L::Button(Button::ActionA, pushed),
+            Event::ActionB(pushed) => L::Button(Button::ActionB, pushed),
+            Event::ActionC(pushed) => L::Button(Button::ActionC, pushed),
+            Event::ActionH(pushed) => L::Button(Button::ActionH, pushed),
+            Event::ActionV(pushed) => L::Button(Button::ActionV, pushed),
+            Event::ActionD(pushed) => L::Button(Button::ActionD, pushed),
+            Event::MenuL(pushed) => L::Button(Button::MenuL, pushed),
+            Event::MenuR(pushed) => L::Button(Button::MenuR, pushed),
+            Event::Joy(pushed) => L::Button(Button::Joy, pushed),
+            Event::Cam(pushed) => L::Button(Button::Cam, pushed),
+            Event::BumperL(pushed) => L::Button(Button::BumperL, pushed),
+            Event::BumperR(pushed) => L::Button(Button::BumperR, pushed),
+            Event::TriggerL(value) => L::Axis(Axis::TriggerL, value),
+            Event::TriggerR(value) => L::Axis(Axis::TriggerR, value),
+            Event::Up(pushed) => L::Button(Button::Up, pushed),
+            Event::Down(pushed) => L::Button(Button::Down, pushed),
+            Event::Left(pushed) => L::Button(Button::Left, pushed),
+            Event::Right(pushed) => L::Button(Button::Right, pushed),
+            Event::PovUp(held) => return Err((Hat::Pov, Direction::Up, held)),
+            Event::PovDown(held) => return Err((Hat::Pov, Direction::Down, held)),
+            Event::PovLeft(held) => return Err((Hat::Pov, Direction::Left, held)),
+            Event::PovRight(held) => return Err((Hat::Pov, Direction::Right, held)),
+            Event::HatUp(held) => return Err((Hat::Extra, Direction::Up, held)),
+            Event::HatDown(held) => return Err((Hat::Extra, Direction::Down, held)),
+            Event::HatLeft(held) => return Err((Hat::Extra, Direction::Left, held)),
+            Event::HatRight(held) => return Err((Hat::Extra, Direction::Right, held)),
+            Event::TrimUp(held) => return Err((Hat::Trim, Direction::Up, held)),
+            Event::TrimDown(held) => return Err((Hat::Trim, Direction::Down, held)),
+            Event::TrimLeft(held) => return Err((Hat::Trim, Direction::Left, held)),
+            Event::TrimRight(held) => return Err((Hat::Trim, Direction::Right, held)),
+            Event::MicUp(held) => return Err((Hat::Mic, Direction::Up, held)),
+            Event::MicDown(held) => return Err((Hat::Mic, Direction::Down, held)),
+            Event::MicLeft(held) => return Err((Hat::Mic, Direction::Left, held)),
+            Event::MicRight(held) => return Err((Hat::Mic, Direction::Right, held)),
+            Event::JoyX(value) => L::Axis(Axis::JoyX, value),
+            Event::JoyY(value) => L::Axis(Axis::JoyY, value),
+            Event::JoyZ(value) => L::Axis(Axis::JoyZ, value),
+            Event::CamX(value) => L::Axis(Axis::CamX, value),
+            Event::CamY(value) => L::Axis(Axis::CamY, value),
+            Event::CamZ(value) => L::Axis(Axis::CamZ, value),
+            Event::Slew(value) => L::Axis(Axis::Slew, value),
+            Event::Throttle(value) => L::Axis(Axis::Throttle, value),
+            Event::ThrottleL(value) => L::Axis(Axis::ThrottleL, value),
+            Event::ThrottleR(value) => L::Axis(Axis::ThrottleR, value),
+            Event::Volume(value) => L::Axis(Axis::Volume, value),
+            Event::Wheel(value) => L::Axis(Axis::Wheel, value),
+            Event::Rudder(value) => L::Axis(Axis::Rudder, value),
+            Event::Gas(value) => L::Axis(Axis::Gas, value),
+            Event::Brake(value) => L::Axis(Axis::Brake, value),
+            Event::MicPush(pushed) => L::Button(Button::MicPush, pushed),
+            Event::Trigger(pushed) => L::Button(Button::Trigger, pushed),
+            Event::Bumper(pushed) => L::Button(Button::Bumper, pushed),
+            Event::ActionM(pushed) => L::Button(Button::ActionM, pushed),
+            Event::ActionL(pushed) => L::Button(Button::ActionL, pushed),
+            Event::ActionR(pushed) => L::Button(Button::ActionR, pushed),
+            Event::Pinky(pushed) => L::Button(Button::Pinky, pushed),
+            Event::PinkyForward(pushed) => L::Button(Button::PinkyForward, pushed),
+            Event::PinkyBackward(pushed) => L::Button(Button::PinkyBackward, pushed),
+            Event::FlapsUp(pushed) => L::Button(Button::FlapsUp, pushed),
+            Event::FlapsDown(pushed) => L::Button(Button::FlapsDown, pushed),
+            Event::BoatForward(pushed) => L::Button(Button::BoatForward, pushed),
+            Event::BoatBackward(pushed) => L::Button(Button::BoatBackward, pushed),
+            Event::AutopilotPath(pushed) => L::Button(Button::AutopilotPath, pushed),
+            Event::AutopilotAlt(pushed) => L::Button(Button::AutopilotAlt, pushed),
+            Event::EngineMotorL(pushed) => L::Button(Button::EngineMotorL, pushed),
+            Event::EngineMotorR(pushed) => L::Button(Button::EngineMotorR, pushed),
+            Event::EngineFuelFlowL(pushed) => L::Button(Button::EngineFuelFlowL, pushed),
+            Event::EngineFuelFlowR(pushed) => L::Button(Button::EngineFuelFlowR, pushed),
+            Event::EngineIgnitionL(pushed) => L::Button(Button::EngineIgnitionL, pushed),
+            Event::EngineIgnitionR(pushed) => L::Button(Button::EngineIgnitionR, pushed),
+            Event::SpeedbrakeBackward(pushed) => L::Button(Button::SpeedbrakeBackward, pushed),
+            Event::SpeedbrakeForward(pushed) => L::Button(Button::SpeedbrakeForward, pushed),
+            Event::ChinaBackward(pushed) => L::Button(Button::ChinaBackward, pushed),
+            Event::ChinaForward(pushed) => L::Button(Button::ChinaForward, pushed),
+            Event::Apu(pushed) => L::Button(Button::Apu, pushed),
+            Event::RadarAltimeter(pushed) => L::Button(Button::RadarAltimeter, pushed),
+            Event::LandingGearSilence(pushed) => L::Button(Button::LandingGearSilence, pushed),
+            Event::Eac(pushed) => L::Button(Button::Eac, pushed),
+            Event::AutopilotToggle(pushed) => L::Button(Button::AutopilotToggle, pushed),
+            Event::ThrottleButton(pushed) => L::Button(Button::ThrottleButton, pushed),
+            Event::MouseX(value) => L::Axis(Axis::MouseX, value),
+            Event::MouseY(value) => L::Axis(Axis::MouseY, value),
+            Event::Mouse(pushed) => L::Button(Button::Mouse, pushed),
+            Event::Number(number, pushed) => L::Button(Button::Number(number), pushed),
+            Event::PaddleLeft(pushed) => L::Button(Button::PaddleLeft, pushed),
+            Event::PaddleRight(pushed) => L::Button(Button::PaddleRight, pushed),
+            Event::PinkyLeft(pushed) => L::Button(Button::PinkyLeft, pushed),
+            Event::PinkyRight(pushed) => L::Button(Button::PinkyRight, pushed),
+            Event::Context(pushed) => L::Button(Button::Context, pushed),
+            Event::Dpi(pushed) => L::Button(Button::Dpi, pushed),
+            Event::ScrollX(value) => L::Axis(Axis::ScrollX, value),
+            Event::ScrollY(value) => L::Axis(Axis::ScrollY, value),
+            Event::Scroll(pushed) => L::Button(Button::Scroll, pushed),
+            Event::ActionWheelX(value) => L::Axis(Axis::ActionWheelX, value),
+            Event::ActionWheelY(value) => L::Axis(Axis::ActionWheelY, value),
+            Event::Repeat(value) => L::Repeat(value),
+            Event::RepeatDelay(value) => L::RepeatDelay(value),
+            Event::RepeatPeriod(value) => L::RepeatPeriod(value),
+            Event::Power(code, value) => L::Power(code, value),
+            Event::Click(value) => L::Click(value),
+            Event::Bell(value) => L::Bell(value),
+            Event::Tone(value) => L::Tone(value),
+        })
+    }
+}
+
+fn button_event<T>(button: Button, pushed: bool) -> Event<T> where T: TryFrom<i32> {
+    match button {
+        Button::Exit => Event::Exit(pushed),
+        Button::ActionA => Event::ActionA(pushed),
+        Button::ActionB => Event::ActionB(pushed),
+        Button::ActionC => Event::ActionC(pushed),
+        Button::ActionH => Event::ActionH(pushed),
+        Button::ActionV => Event::ActionV(pushed),
+        Button::ActionD => Event::ActionD(pushed),
+        Button::MenuL => Event::MenuL(pushed),
+        Button::MenuR => Event::MenuR(pushed),
+        Button::Joy => Event::Joy(pushed),
+        Button::Cam => Event::Cam(pushed),
+        Button::BumperL => Event::BumperL(pushed),
+        Button::BumperR => Event::BumperR(pushed),
+        Button::Up => Event::Up(pushed),
+        Button::Down => Event::Down(pushed),
+        Button::Left => Event::Left(pushed),
+        Button::Right => Event::Right(pushed),
+        Button::MicPush => Event::MicPush(pushed),
+        Button::Trigger => Event::Trigger(pushed),
+        Button::Bumper => Event::Bumper(pushed),
+        Button::ActionM => Event::ActionM(pushed),
+        Button::ActionL => Event::ActionL(pushed),
+        Button::ActionR => Event::ActionR(pushed),
+        Button::Pinky => Event::Pinky(pushed),
+        Button::PinkyForward => Event::PinkyForward(pushed),
+        Button::PinkyBackward => Event::PinkyBackward(pushed),
+        Button::FlapsUp => Event::FlapsUp(pushed),
+        Button::FlapsDown => Event::FlapsDown(pushed),
+        Button::BoatForward => Event::BoatForward(pushed),
+        Button::BoatBackward => Event::BoatBackward(pushed),
+        Button::AutopilotPath => Event::AutopilotPath(pushed),
+        Button::AutopilotAlt => Event::AutopilotAlt(pushed),
+        Button::EngineMotorL => Event::EngineMotorL(pushed),
+        Button::EngineMotorR => Event::EngineMotorR(pushed),
+        Button::EngineFuelFlowL => Event::EngineFuelFlowL(pushed),
+        Button::EngineFuelFlowR => Event::EngineFuelFlowR(pushed),
+        Button::EngineIgnitionL => Event::EngineIgnitionL(pushed),
+        Button::EngineIgnitionR => Event::EngineIgnitionR(pushed),
+        Button::SpeedbrakeBackward => Event::SpeedbrakeBackward(pushed),
+        Button::SpeedbrakeForward => Event::SpeedbrakeForward(pushed),
+        Button::ChinaBackward => Event::ChinaBackward(pushed),
+        Button::ChinaForward => Event::ChinaForward(pushed),
+        Button::Apu => Event::Apu(pushed),
+        Button::RadarAltimeter => Event::RadarAltimeter(pushed),
+        Button::LandingGearSilence => Event::LandingGearSilence(pushed),
+        Button::Eac => Event::Eac(pushed),
+        Button::AutopilotToggle => Event::AutopilotToggle(pushed),
+        Button::ThrottleButton => Event::ThrottleButton(pushed),
+        Button::Mouse => Event::Mouse(pushed),
+        Button::Number(number) => Event::Number(number, pushed),
+        Button::PaddleLeft => Event::PaddleLeft(pushed),
+        Button::PaddleRight => Event::PaddleRight(pushed),
+        Button::PinkyLeft => Event::PinkyLeft(pushed),
+        Button::PinkyRight => Event::PinkyRight(pushed),
+        Button::Context => Event::Context(pushed),
+        Button::Dpi => Event::Dpi(pushed),
+        Button::Scroll => Event::Scroll(pushed),
+    }
+}
+
+fn axis_event<T>(axis: Axis, value: T) -> Event<T> where T: TryFrom<i32> {
+    match axis {
+        Axis::TriggerL => Event::TriggerL(value),
+        Axis::TriggerR => Event::TriggerR(value),
+        Axis::JoyX => Event::JoyX(value),
+        Axis::JoyY => Event::JoyY(value),
+        Axis::JoyZ => Event::JoyZ(value),
+        Axis::CamX => Event::CamX(value),
+        Axis::CamY => Event::CamY(value),
+        Axis::CamZ => Event::CamZ(value),
+        Axis::Slew => Event::Slew(value),
+        Axis::Throttle => Event::Throttle(value),
+        Axis::ThrottleL => Event::ThrottleL(value),
+        Axis::ThrottleR => Event::ThrottleR(value),
+        Axis::Volume => Event::Volume(value),
+        Axis::Wheel => Event::Wheel(value),
+        Axis::Rudder => Event::Rudder(value),
+        Axis::Gas => Event::Gas(value),
+        Axis::Brake => Event::Brake(value),
+        Axis::MouseX => Event::MouseX(value),
+        Axis::MouseY => Event::MouseY(value),
+        Axis::ScrollX => Event::ScrollX(value),
+        Axis::ScrollY => Event::ScrollY(value),
+        Axis::ActionWheelX => Event::ActionWheelX(value),
+        Axis::ActionWheelY => Event::ActionWheelY(value),
+    }
+}
+
+/// The flat event of one direction of a hat.  `direction` has to be one of
+/// `Up`, `Down`, `Left` or `Right`.
+fn hat_event<T>(hat: Hat, direction: Direction, held: bool) -> Option<Event<T>> where T: TryFrom<i32> {
+    let events: [fn(bool) -> Event<T>; 4] = match hat {
+        Hat::Pov => [Event::PovUp, Event::PovDown, Event::PovLeft, Event::PovRight],
+        Hat::Extra => [Event::HatUp, Event::HatDown, Event::HatLeft, Event::HatRight],
+        Hat::Trim => [Event::TrimUp, Event::TrimDown, Event::TrimLeft, Event::TrimRight],
+        Hat::Mic => [Event::MicUp, Event::MicDown, Event::MicLeft, Event::MicRight],
+    };
+    let index = match direction {
+        Direction::Up => 0,
+        Direction::Down => 1,
+        Direction::Left => 2,
+        Direction::Right => 3,
+        _ => return None,
+    };
+    Some(events[index](held))
+}
+
+/// Translates between flat and layered events.
+///
+/// Buttons and axes translate one to one.  A flat hat event only carries
+/// one direction, so the converter remembers where each hat points to
+/// report its full [`Direction`], and to split a layered hat event into the
+/// flat releases and presses that lead to it.
+#[derive(Debug, Clone, Default)]
+pub struct Converter {
+    hats: HatState,
+}
+
+impl Converter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current direction of a hat.
+    pub fn hat(&self, hat: Hat) -> Direction {
+        self.hats.direction(hat)
+    }
+
+    /// The layered event of a flat one.
+    pub fn layer<T>(&mut self, event: Event<T>) -> crate::Event<T> where T: TryFrom<i32> {
+        let (hat, direction, held) = match event.split() {
+            Ok(event) => return event,
+            Err(hat) => hat,
+        };
+        let current = self.hats.direction(hat);
+        let (mut x, mut y) = (current.x(), current.y());
+        let (dx, dy) = (direction.x(), direction.y());
+        if dx != 0 {
+            x = if held { dx } else if x == dx { 0 } else { x };
+        }
+        if dy != 0 {
+            y = if held { dy } else if y == dy { 0 } else { y };
+        }
+        let direction = Direction::from_xy(x, y);
+        self.hats.set(hat, direction);
+        crate::Event::Hat(hat, direction)
+    }
+
+    /// The flat events for a layered one.  A hat moving from `Up` to
+    /// `Right` gives `PovUp(false)` and `PovRight(true)`.
+    pub fn flatten<T>(&mut self, event: crate::Event<T>) -> Vec<Event<T>> where T: TryFrom<i32> {
+        use crate::Event as L;
+        let event = match event {
+            L::Disconnect => Event::Disconnect,
+            L::Button(button, pushed) => button_event(button, pushed),
+            L::Axis(axis, value) => axis_event(axis, value),
+            L::Hat(hat, direction) => {
+                let changes = self.hats.set(hat, direction);
+                return changes.into_iter().filter_map(|(direction, held)| hat_event(hat, direction, held)).collect();
+            }
+            L::Repeat(code) => Event::Repeat(code),
+            L::RepeatDelay(value) => Event::RepeatDelay(value),
+            L::RepeatPeriod(value) => Event::RepeatPeriod(value),
+            L::Power(code, value) => Event::Power(code, value),
+            L::Click(on) => Event::Click(on),
+            L::Bell(on) => Event::Bell(on),
+            L::Tone(value) => Event::Tone(value),
+        };
+        vec![event]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use input_linux_sys::{timeval, ABS_HAT0Y, ABS_HAT1Y, ABS_X, BTN_SOUTH, EV_KEY};
+
+    use super::*;
+    use crate::EventKind;
+
+    const BUTTONS: [Button; 57] = [
+        Button::Exit, Button::ActionA, Button::ActionB, Button::ActionC, Button::ActionH,
+        Button::ActionV, Button::ActionD, Button::MenuL, Button::MenuR, Button::Joy, Button::Cam,
+        Button::BumperL, Button::BumperR, Button::Up, Button::Down, Button::Left, Button::Right,
+        Button::MicPush, Button::Trigger, Button::Bumper, Button::ActionM, Button::ActionL,
+        Button::ActionR, Button::Pinky, Button::PinkyForward, Button::PinkyBackward,
+        Button::FlapsUp, Button::FlapsDown, Button::BoatForward, Button::BoatBackward,
+        Button::AutopilotPath, Button::AutopilotAlt, Button::EngineMotorL, Button::EngineMotorR,
+        Button::EngineFuelFlowL, Button::EngineFuelFlowR, Button::EngineIgnitionL,
+        Button::EngineIgnitionR, Button::SpeedbrakeBackward, Button::SpeedbrakeForward,
+        Button::ChinaBackward, Button::ChinaForward, Button::Apu, Button::RadarAltimeter,
+        Button::LandingGearSilence, Button::Eac, Button::AutopilotToggle, Button::ThrottleButton,
+        Button::Mouse, Button::Number(3), Button::PaddleLeft, Button::PaddleRight,
+        Button::PinkyLeft, Button::PinkyRight, Button::Context, Button::Dpi, Button::Scroll,
+    ];
+
+    fn others() -> [crate::Event; 8] {
+        use crate::Event as L;
+        [
+            L::Disconnect, L::Repeat(30), L::RepeatDelay(250), L::RepeatPeriod(33),
+            L::Power(0x74, 1), L::Click(true), L::Bell(true), L::Tone(440),
+        ]
+    }
+
+    fn raw(type_: i32, code: i32, value: i32) -> input_event {
+        input_event { time: timeval { tv_sec: 0, tv_usec: 0 }, type_: type_ as u16, code: code as u16, value }
+    }
+
+    #[test]
+    fn every_variant_both_ways() {
+        let mut converter = Converter::new();
+        let mut kinds = BTreeSet::new();
+        let layered = BUTTONS.iter().flat_map(|&b| [crate::Event::Button(b, true), crate::Event::Button(b, false)])
+            .chain(Axis::ALL.iter().map(|&a| crate::Event::Axis(a, -7)))
+            .chain(others());
+        for event in layered {
+            let flat = converter.flatten(event);
+            assert_eq!(flat.len(), 1, "{event:?}");
+            kinds.insert(flat[0].kind());
+            assert_eq!(converter.layer(flat[0]), event);
+        }
+        for hat in Hat::ALL {
+            for direction in [Direction::Up, Direction::Down, Direction::Left, Direction::Right] {
+                let flat = converter.flatten(crate::Event::<i32>::Hat(hat, direction));
+                assert_eq!(flat.len(), 1, "{hat:?} {direction:?}");
+                kinds.insert(flat[0].kind());
+                assert_eq!(converter.layer(flat[0]), crate::Event::Hat(hat, direction));
+                let released = converter.flatten(crate::Event::<i32>::Hat(hat, Direction::Center));
+                assert_eq!(released.len(), 1);
+                assert_eq!(released[0].kind(), flat[0].kind());
+                assert_eq!(converter.layer(released[0]), crate::Event::Hat(hat, Direction::Center));
+            }
+        }
+        assert_eq!(kinds, BTreeSet::from(EventKind::ALL));
+    }
+
+    #[test]
+    fn hat_transitions() {
+        let mut converter = Converter::new();
+        assert_eq!(converter.flatten(crate::Event::<i32>::Hat(Hat::Pov, Direction::Up)), [Event::PovUp(true)]);
+        assert_eq!(converter.flatten(crate::Event::<i32>::Hat(Hat::Pov, Direction::UpRight)), [Event::PovRight(true)]);
+        let flat = converter.flatten(crate::Event::<i32>::Hat(Hat::Pov, Direction::DownLeft));
+        assert_eq!(flat, [Event::PovRight(false), Event::PovUp(false), Event::PovLeft(true), Event::PovDown(true)]);
+        assert_eq!(converter.hat(Hat::Pov), Direction::DownLeft);
+        assert_eq!(converter.hat(Hat::Trim), Direction::Center);
+
+        // Layering the same events walks through every step in between.
+        let mut converter = Converter::new();
+        let steps: Vec<_> = [Event::PovUp(true), Event::PovRight(true)]
+            .into_iter()
+            .chain(flat)
+            .map(|event| converter.layer(event))
+            .collect();
+        let directions = [Direction::Up, Direction::UpRight, Direction::Up, Direction::Center, Direction::Left, Direction::DownLeft];
+        assert_eq!(steps, directions.map(|d| crate::Event::<i32>::Hat(Hat::Pov, d)));
+
+        // Releasing a direction that isn't held leaves the hat alone.
+        assert_eq!(converter.layer(Event::<i32>::PovRight(false)), crate::Event::Hat(Hat::Pov, Direction::DownLeft));
+    }
+
+    #[test]
+    fn decoding_agrees() {
+        let events = [
+            raw(EV_ABS, ABS_HAT0X, -1),
+            raw(EV_ABS, ABS_HAT0Y, 1),
+            raw(EV_ABS, ABS_HAT0X, 0),
+            raw(EV_ABS, ABS_HAT0Y, 0),
+            raw(EV_ABS, ABS_HAT1Y, -1),
+            raw(EV_ABS, ABS_X, 300),
+            raw(EV_KEY, BTN_SOUTH, 1),
+            raw(EV_ABS, ABS_HAT1Y, 0),
+        ];
+        let (mut state, mut hats, mut converter) = (0, HatState::new(), Converter::new());
+        let mut flat = Vec::new();
+        for event in &events {
+            let layered = crate::Event::<i32>::from_input_event(event, &mut hats).unwrap();
+            let decoded = Event::from_input_event(event, &mut state).unwrap();
+            assert_eq!(converter.layer(decoded), layered);
+            flat.push(decoded);
+        }
+        assert_eq!(flat, [
+            Event::PovLeft(true),
+            Event::PovDown(true),
+            Event::PovLeft(false),
+            Event::PovDown(false),
+            Event::HatUp(true),
+            Event::JoyX(300),
+            Event::ActionA(true),
+            Event::HatUp(false),
+        ]);
+    }
+}
@@ -0,0 +1,235 @@
+//! Tap, double tap and long press recognition on buttons.
+
+use std::time::Duration;
+
+use crate::time::Timestamp;
+use crate::{Button, Event};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Gesture {
+    /// Short press and release, not followed by a second one.
+    Tap,
+    /// Two taps in quick succession, reported on the second release.
+    DoubleTap,
+    /// The button has been held for the long press time, reported while it
+    /// is still held.
+    LongPress,
+    /// A press longer than a tap ended, after being held this long.
+    Hold(Duration),
+}
+
+/// A recognized gesture and its button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GestureEvent {
+    pub button: Button,
+    pub gesture: Gesture,
+}
+
+/// Timing thresholds of gesture recognition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GestureConfig {
+    /// Longest press that still counts as a tap.
+    pub tap: Duration,
+    /// Time between the first release and the second press of a double
+    /// tap, which has to be shorter.  Zero disables double taps, so taps
+    /// are reported without waiting.
+    pub double_tap: Duration,
+    /// Time a button has to be held to be a long press.
+    pub long_press: Duration,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap: Duration::from_millis(200),
+            double_tap: Duration::from_millis(250),
+            long_press: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ButtonState {
+    pressed_at: Option<Timestamp>,
+    long_press: bool,
+    /// Release time of a tap that may become a double tap.
+    tap: Option<Timestamp>,
+    /// Whether the current press started within the double tap gap.
+    second: bool,
+}
+
+/// Recognizes [`Gesture`]s on any button.
+///
+/// Time comes only from the timestamps passed in, so a recorded stream
+/// always gives the same gestures.  Gestures that are only known once time
+/// has passed (taps waiting out the double tap gap, long presses) are
+/// reported by the next [`GestureRecognizer::feed`] or by
+/// [`GestureRecognizer::poll`].
+#[derive(Debug)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    /// In first seen order, so simultaneous gestures are always reported
+    /// in the same order.
+    buttons: Vec<(Button, ButtonState)>,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self { config, buttons: Vec::new() }
+    }
+
+    pub fn config(&self) -> &GestureConfig {
+        &self.config
+    }
+
+    /// When the next time based gesture is due, if any.
+    pub fn next_deadline(&self) -> Option<Timestamp> {
+        self.buttons.iter().filter_map(|(_, state)| {
+            match (state.pressed_at, state.tap) {
+                (Some(at), _) if !state.long_press => Some(at + self.config.long_press),
+                (None, Some(tap)) => Some(tap + self.config.double_tap),
+                _ => None,
+            }
+        }).min()
+    }
+
+    /// Report the gestures that are complete by `now`.
+    pub fn poll(&mut self, now: Timestamp) -> Vec<GestureEvent> {
+        let mut out = Vec::new();
+        for (button, state) in &mut self.buttons {
+            if let Some(at) = state.pressed_at {
+                if !state.long_press && now.duration_since(at) >= self.config.long_press {
+                    state.long_press = true;
+                    out.push(GestureEvent { button: *button, gesture: Gesture::LongPress });
+                }
+            } else if let Some(tap) = state.tap {
+                if now.duration_since(tap) >= self.config.double_tap {
+                    state.tap = None;
+                    out.push(GestureEvent { button: *button, gesture: Gesture::Tap });
+                }
+            }
+        }
+        out
+    }
+
+    pub fn feed<T>(&mut self, time: Timestamp, event: Event<T>) -> Vec<GestureEvent> where T: TryFrom<i32> {
+        let mut out = self.poll(time);
+        let Event::Button(button, pushed) = event else {
+            return out;
+        };
+        let index = match self.buttons.iter().position(|(b, _)| *b == button) {
+            Some(index) => index,
+            None => {
+                let state = ButtonState { pressed_at: None, long_press: false, tap: None, second: false };
+                self.buttons.push((button, state));
+                self.buttons.len() - 1
+            }
+        };
+        let state = &mut self.buttons[index].1;
+        if pushed {
+            if state.pressed_at.is_none() {
+                state.pressed_at = Some(time);
+                state.long_press = false;
+                state.second = state.tap.is_some();
+            }
+            return out;
+        }
+        let Some(pressed_at) = state.pressed_at.take() else {
+            return out;
+        };
+        let held = time.duration_since(pressed_at);
+        if held > self.config.tap {
+            if state.tap.take().is_some() {
+                out.push(GestureEvent { button, gesture: Gesture::Tap });
+            }
+            out.push(GestureEvent { button, gesture: Gesture::Hold(held) });
+        } else if state.second {
+            state.tap = None;
+            out.push(GestureEvent { button, gesture: Gesture::DoubleTap });
+        } else if self.config.double_tap.is_zero() {
+            out.push(GestureEvent { button, gesture: Gesture::Tap });
+        } else {
+            state.tap = Some(time);
+        }
+        state.second = false;
+        out
+    }
+}
+
+impl Default for GestureRecognizer {
+    fn default() -> Self {
+        Self::new(GestureConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::at;
+
+    fn action_a(pushed: bool) -> Event {
+        Event::Button(Button::ActionA, pushed)
+    }
+
+    fn gestures(events: Vec<GestureEvent>) -> Vec<Gesture> {
+        events.into_iter().map(|event| event.gesture).collect()
+    }
+
+    #[test]
+    fn tap_at_deadline() {
+        let mut recognizer = GestureRecognizer::default();
+        assert!(recognizer.feed(at(0), action_a(true)).is_empty());
+        assert!(recognizer.feed(at(100), action_a(false)).is_empty());
+        let deadline = recognizer.next_deadline().unwrap();
+        assert_eq!(deadline, at(350));
+        assert!(recognizer.poll(at(349)).is_empty());
+        let events = recognizer.poll(deadline);
+        assert_eq!(events, [GestureEvent { button: Button::ActionA, gesture: Gesture::Tap }]);
+        assert_eq!(recognizer.next_deadline(), None);
+        assert!(recognizer.poll(deadline).is_empty());
+    }
+
+    #[test]
+    fn second_press_at_deadline_is_not_a_double_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.feed(at(0), action_a(true));
+        recognizer.feed(at(100), action_a(false));
+        assert_eq!(gestures(recognizer.feed(at(350), action_a(true))), [Gesture::Tap]);
+        assert!(recognizer.feed(at(400), action_a(false)).is_empty());
+    }
+
+    #[test]
+    fn double_tap() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.feed(at(0), action_a(true));
+        recognizer.feed(at(100), action_a(false));
+        assert!(recognizer.feed(at(349), action_a(true)).is_empty());
+        assert_eq!(gestures(recognizer.feed(at(400), action_a(false))), [Gesture::DoubleTap]);
+        assert_eq!(recognizer.next_deadline(), None);
+    }
+
+    #[test]
+    fn long_press_at_deadline() {
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.feed(at(0), action_a(true));
+        let deadline = recognizer.next_deadline().unwrap();
+        assert_eq!(deadline, at(500));
+        assert!(recognizer.poll(at(499)).is_empty());
+        assert_eq!(gestures(recognizer.poll(deadline)), [Gesture::LongPress]);
+        assert_eq!(recognizer.next_deadline(), None);
+        let hold = Gesture::Hold(Duration::from_millis(800));
+        assert_eq!(gestures(recognizer.feed(at(800), action_a(false))), [hold]);
+    }
+
+    #[test]
+    fn taps_without_double_tap() {
+        let config = GestureConfig { double_tap: Duration::ZERO, ..GestureConfig::default() };
+        let mut recognizer = GestureRecognizer::new(config);
+        recognizer.feed(at(0), action_a(true));
+        assert_eq!(gestures(recognizer.feed(at(50), action_a(false))), [Gesture::Tap]);
+        assert_eq!(recognizer.next_deadline(), None);
+    }
+}
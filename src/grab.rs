@@ -0,0 +1,129 @@
+//! Exclusive access to a device (`EVIOCGRAB`).
+
+use std::io;
+use std::mem::zeroed;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::AsRawFd;
+use std::time::{Duration, Instant};
+
+use input_linux_sys::*;
+use nix::poll::{poll, PollFd, PollFlags};
+
+use crate::device::{bitmask_len, check};
+use crate::Device;
+
+impl Device {
+    /// Stop events of the device from reaching anyone else, e.g. the
+    /// desktop, until the returned guard is dropped.
+    ///
+    /// Keys held while grabbing stay pressed for everyone else, see
+    /// [`Device::grab_when_released`].
+    pub fn grab(&mut self) -> io::Result<Grab<'_>> {
+        check(unsafe { ev_grab(self.as_raw_fd(), 1) })?;
+        Ok(Grab { device: self })
+    }
+
+    /// Release a grab taken with [`Device::grab`] whose guard was leaked.
+    pub fn ungrab(&self) -> io::Result<()> {
+        check(unsafe { ev_grab(self.as_raw_fd(), 0) })?;
+        Ok(())
+    }
+
+    /// Wait until no key or button of the device is held, then grab it, so
+    /// the desktop sees every release.  Events read while waiting are
+    /// discarded.  Fails with [`io::ErrorKind::TimedOut`] if keys are still
+    /// held after `timeout`.
+    pub fn grab_when_released(&mut self, timeout: Option<Duration>) -> io::Result<Grab<'_>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if !self.any_key_held()? {
+                check(unsafe { ev_grab(self.as_raw_fd(), 1) })?;
+                // A key may have gone down in between.
+                if !self.any_key_held()? {
+                    return Ok(Grab { device: self });
+                }
+                self.ungrab()?;
+            }
+            let wait = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "keys still held"));
+                    }
+                    left.as_millis().min(i32::MAX as u128) as i32
+                }
+                None => -1,
+            };
+            let mut fds = [PollFd::new(self.as_raw_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, wait) {
+                Ok(_) | Err(nix::errno::Errno::EINTR) => {}
+                Err(err) => return Err(err.into()),
+            }
+            if fds[0].revents().is_some_and(|events| events.contains(PollFlags::POLLIN)) {
+                self.discard()?;
+            }
+        }
+    }
+
+    /// Bitmask of the keys and buttons currently held (`EVIOCGKEY`).
+    pub fn key_state(&self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; bitmask_len(KEY_CNT as _)];
+        check(unsafe { ev_get_key(self.as_raw_fd(), &mut buf) })?;
+        Ok(buf)
+    }
+
+    fn any_key_held(&self) -> io::Result<bool> {
+        Ok(self.key_state()?.iter().any(|byte| *byte != 0))
+    }
+
+    /// Read and drop the pending events.  Only hat events are decoded, to
+    /// keep the hat state in step; everything else, such as keyboard keys,
+    /// is dropped raw.
+    fn discard(&mut self) -> io::Result<()> {
+        // Safety: input_event is plain old data.
+        let mut buf: [input_event; 64] = unsafe { zeroed() };
+        let count = self.read_raw(&mut buf)?;
+        for raw in &buf[..count] {
+            if raw.type_ as i32 == EV_ABS && (ABS_HAT0X..=ABS_HAT3Y).contains(&(raw.code as i32)) {
+                let _ = self.decode::<i32>(raw);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An exclusive grab of a [`Device`], released when dropped, including
+/// when unwinding from a panic.
+#[derive(Debug)]
+pub struct Grab<'a> {
+    device: &'a mut Device,
+}
+
+impl Grab<'_> {
+    /// Release the grab, reporting errors that dropping would ignore.
+    pub fn release(self) -> io::Result<()> {
+        let result = self.device.ungrab();
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Deref for Grab<'_> {
+    type Target = Device;
+
+    fn deref(&self) -> &Device {
+        self.device
+    }
+}
+
+impl DerefMut for Grab<'_> {
+    fn deref_mut(&mut self) -> &mut Device {
+        self.device
+    }
+}
+
+impl Drop for Grab<'_> {
+    fn drop(&mut self) {
+        let _ = self.device.ungrab();
+    }
+}
@@ -0,0 +1,249 @@
+//! Device identity and IDs that stay the same across reboots and replugs,
+//! unlike `/dev/input/eventN` paths.
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::{Device, InputId};
+
+/// Everything a device says about itself.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Identity {
+    /// `EVIOCGNAME`
+    pub name: String,
+    /// Physical location, e.g. `usb-0000:00:14.0-2/input0` (`EVIOCGPHYS`).
+    pub phys: Option<String>,
+    /// Unique identifier, usually a serial number or Bluetooth address
+    /// (`EVIOCGUNIQ`).
+    pub uniq: Option<String>,
+    /// `EVIOCGID`
+    pub id: InputId,
+}
+
+impl Identity {
+    pub fn of(device: &Device) -> io::Result<Self> {
+        Ok(Self { name: device.name()?, phys: device.phys()?, uniq: device.uniq()?, id: device.id()? })
+    }
+
+    /// The best ID of this device alone.  Use [`StableId::assign`] when there
+    /// may be several identical devices.
+    pub fn stable_id(&self) -> StableId {
+        if let Some(uniq) = &self.uniq {
+            self.serial(uniq, 0)
+        } else if let Some(phys) = &self.phys {
+            StableId::Phys(phys.clone())
+        } else {
+            self.model(0)
+        }
+    }
+
+    fn serial(&self, uniq: &str, node: usize) -> StableId {
+        StableId::Serial { vendor: self.id.vendor, product: self.id.product, uniq: uniq.to_owned(), node }
+    }
+
+    fn model(&self, index: usize) -> StableId {
+        StableId::Model { bustype: self.id.bustype, vendor: self.id.vendor, product: self.id.product, index }
+    }
+}
+
+/// An ID to key per-device settings by.
+///
+/// In order of preference it is built from the serial number, which follows
+/// the device to any port, from the physical path, which stays the same as
+/// long as the device is plugged into the same port, or from the model plus
+/// an index among identical devices, which only tells devices of different
+/// models apart reliably.
+///
+/// One device may have several nodes with the same serial number, e.g. a
+/// gamepad and its motion sensors, told apart by `node`, their index in
+/// name order.
+///
+/// The string form, e.g. `serial:045e:028e:0:3032363030`, is meant for
+/// configuration files.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StableId {
+    Serial { vendor: u16, product: u16, uniq: String, node: usize },
+    Phys(String),
+    Model { bustype: u16, vendor: u16, product: u16, index: usize },
+}
+
+impl StableId {
+    /// IDs of a group of devices, e.g. all those currently plugged in, in
+    /// the order given.  Model indices are assigned in path order, so they
+    /// don't depend on the order of `devices`.  Cheap devices often report
+    /// made-up serial numbers and physical paths, so a serial number is only
+    /// used if all devices sharing it are nodes of one device, on the same
+    /// physical path, and a physical path only if no other device shares it.
+    pub fn assign(devices: &[(PathBuf, Identity)]) -> Vec<StableId> {
+        let unique = |value: &Option<String>, field: fn(&Identity) -> &Option<String>| {
+            value.is_some() && devices.iter().filter(|(_, other)| field(other) == value).count() == 1
+        };
+        let serial = |(path, identity): &(PathBuf, Identity)| {
+            let uniq = identity.uniq.as_ref()?;
+            let nodes: Vec<_> = devices.iter().filter(|(_, other)| other.uniq.as_ref() == Some(uniq)).collect();
+            if nodes.len() > 1 && nodes.iter().any(|(_, other)| identity.phys.is_none() || other.phys != identity.phys) {
+                return None;
+            }
+            let node = nodes.iter().filter(|(other_path, other)| (&other.name, other_path) < (&identity.name, path)).count();
+            Some(identity.serial(uniq, node))
+        };
+        let mut order: Vec<usize> = (0..devices.len()).collect();
+        order.sort_by(|a, b| devices[*a].0.cmp(&devices[*b].0));
+        let mut ids = vec![None; devices.len()];
+        let mut models: Vec<(u16, u16, u16)> = Vec::new();
+        for index in order {
+            let identity = &devices[index].1;
+            let id = if let Some(id) = serial(&devices[index]) {
+                id
+            } else if unique(&identity.phys, |identity| &identity.phys) {
+                StableId::Phys(identity.phys.clone().unwrap_or_default())
+            } else {
+                let model = (identity.id.bustype, identity.id.vendor, identity.id.product);
+                let index = models.iter().filter(|other| **other == model).count();
+                models.push(model);
+                identity.model(index)
+            };
+            ids[index] = Some(id);
+        }
+        ids.into_iter().flatten().collect()
+    }
+}
+
+impl fmt::Display for StableId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StableId::Serial { vendor, product, uniq, node } => {
+                write!(f, "serial:{vendor:04x}:{product:04x}:{node}:{uniq}")
+            }
+            StableId::Phys(phys) => write!(f, "phys:{phys}"),
+            StableId::Model { bustype, vendor, product, index } => {
+                write!(f, "model:{bustype:04x}:{vendor:04x}:{product:04x}:{index}")
+            }
+        }
+    }
+}
+
+impl FromStr for StableId {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid device id: {s}"));
+        let hex = |part: Option<&str>| part.and_then(|part| u16::from_str_radix(part, 16).ok()).ok_or_else(invalid);
+        let (kind, rest) = s.split_once(':').ok_or_else(invalid)?;
+        Ok(match kind {
+            "serial" => {
+                let mut parts = rest.splitn(4, ':');
+                let (vendor, product) = (hex(parts.next())?, hex(parts.next())?);
+                let node = parts.next().and_then(|node| node.parse().ok()).ok_or_else(invalid)?;
+                let uniq = parts.next().ok_or_else(invalid)?.to_owned();
+                StableId::Serial { vendor, product, uniq, node }
+            }
+            "phys" => StableId::Phys(rest.to_owned()),
+            "model" => {
+                let mut parts = rest.split(':');
+                let (bustype, vendor, product) = (hex(parts.next())?, hex(parts.next())?, hex(parts.next())?);
+                let index = parts.next().and_then(|index| index.parse().ok()).ok_or_else(invalid)?;
+                if parts.next().is_some() {
+                    return Err(invalid());
+                }
+                StableId::Model { bustype, vendor, product, index }
+            }
+            _ => return Err(invalid()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(phys: Option<&str>, uniq: Option<&str>) -> Identity {
+        Identity {
+            name: "Pad".to_owned(),
+            phys: phys.map(str::to_owned),
+            uniq: uniq.map(str::to_owned),
+            id: InputId { bustype: 0x03, vendor: 0x045e, product: 0x028e, version: 0x0114 },
+        }
+    }
+
+    fn model(index: usize) -> StableId {
+        StableId::Model { bustype: 0x03, vendor: 0x045e, product: 0x028e, index }
+    }
+
+    #[test]
+    fn stable_id_preference() {
+        let serial = StableId::Serial { vendor: 0x045e, product: 0x028e, uniq: "abc".to_owned(), node: 0 };
+        assert_eq!(identity(Some("usb-1"), Some("abc")).stable_id(), serial);
+        assert_eq!(identity(Some("usb-1"), None).stable_id(), StableId::Phys("usb-1".to_owned()));
+        assert_eq!(identity(None, None).stable_id(), model(0));
+    }
+
+    #[test]
+    fn assign_indices_in_path_order() {
+        let devices = [
+            (PathBuf::from("/dev/input/event7"), identity(None, Some("0000"))),
+            (PathBuf::from("/dev/input/event3"), identity(Some("usb-2"), None)),
+            (PathBuf::from("/dev/input/event5"), identity(None, Some("0000"))),
+            (PathBuf::from("/dev/input/event4"), identity(Some("usb-1"), None)),
+        ];
+        let expected = [model(1), StableId::Phys("usb-2".to_owned()), model(0), StableId::Phys("usb-1".to_owned())];
+        assert_eq!(StableId::assign(&devices), expected);
+
+        let mut reversed = devices.to_vec();
+        reversed.reverse();
+        let mut expected = expected.to_vec();
+        expected.reverse();
+        assert_eq!(StableId::assign(&reversed), expected);
+    }
+
+    #[test]
+    fn assign_shared_phys_falls_back_to_model() {
+        let devices = [
+            (PathBuf::from("/dev/input/event2"), identity(Some("usb-1"), None)),
+            (PathBuf::from("/dev/input/event1"), identity(Some("usb-1"), None)),
+        ];
+        assert_eq!(StableId::assign(&devices), [model(1), model(0)]);
+    }
+
+    #[test]
+    fn assign_nodes_sharing_a_serial() {
+        let mut motion = identity(Some("usb-1"), Some("a4:5e:60:01:02:03"));
+        motion.name = "Pad Motion Sensors".to_owned();
+        let devices = [
+            (PathBuf::from("/dev/input/event9"), identity(Some("usb-1"), Some("a4:5e:60:01:02:03"))),
+            (PathBuf::from("/dev/input/event8"), motion),
+        ];
+        let serial = |node| StableId::Serial { vendor: 0x045e, product: 0x028e, uniq: "a4:5e:60:01:02:03".to_owned(), node };
+        assert_eq!(StableId::assign(&devices), [serial(0), serial(1)]);
+    }
+
+    #[test]
+    fn assign_shared_serial_on_different_paths_is_not_used() {
+        let devices = [
+            (PathBuf::from("/dev/input/event2"), identity(Some("usb-2"), Some("0000"))),
+            (PathBuf::from("/dev/input/event1"), identity(Some("usb-1"), Some("0000"))),
+        ];
+        assert_eq!(StableId::assign(&devices), [StableId::Phys("usb-2".to_owned()), StableId::Phys("usb-1".to_owned())]);
+    }
+
+    #[test]
+    fn string_round_trip() {
+        let ids = [
+            StableId::Serial { vendor: 0x045e, product: 0x028e, uniq: "a4:5e:60:01:02:03".to_owned(), node: 1 },
+            StableId::Phys("usb-0000:00:14.0-2/input0".to_owned()),
+            model(3),
+        ];
+        for id in ids {
+            assert_eq!(id.to_string().parse::<StableId>().unwrap(), id);
+        }
+        assert_eq!(model(3).to_string(), "model:0003:045e:028e:3");
+        let invalid = ["", "model:3:045e:028e", "model:0003:045e:028e:1:2", "serial:045e", "serial:045e:028e:abc", "usb:1"];
+        for invalid in invalid {
+            assert!(invalid.parse::<StableId>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! The legacy joystick interface (`/dev/input/jsN`).
+//!
+//! Joydev reports axes and buttons by index.  [`Joydev`] maps the indices
+//! back to evdev codes with `JSIOCGAXMAP` / `JSIOCGBTNMAP` and decodes them
+//! like [`crate::Device`] does, so the same [`Event`]s come out of either
+//! interface.  Joydev scales every axis to `-32767..=32767`, and the values
+//! are passed on in that scale; [`AXIS_INFO`] describes it, e.g. for
+//! [`StickConfig::from_absinfo`](crate::axis::StickConfig::from_absinfo).
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem::size_of;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+
+use input_linux_sys::*;
+use nix::libc::{c_char, ioctl};
+use nix::{convert_ioctl_res, request_code_read};
+
+use crate::device::{c_string, check, is_disconnect};
+use crate::{AbsInfo, Event, HatState};
+
+/// Button pressed or released.
+pub const JS_EVENT_BUTTON: u8 = 0x01;
+/// Axis moved.
+pub const JS_EVENT_AXIS: u8 = 0x02;
+/// Flag of the synthetic events reporting the initial state after open.
+pub const JS_EVENT_INIT: u8 = 0x80;
+
+/// Range of every joydev axis, whatever the range of the evdev axis.
+pub const AXIS_INFO: AbsInfo = AbsInfo { value: 0, minimum: -32767, maximum: 32767, fuzz: 0, flat: 0, resolution: 0 };
+
+/// `struct js_event`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(C)]
+pub struct JsEvent {
+    /// Milliseconds, from an arbitrary start.
+    pub time: u32,
+    pub value: i16,
+    pub type_: u8,
+    /// Index of the axis or button.
+    pub number: u8,
+}
+
+impl JsEvent {
+    pub fn from_bytes(bytes: [u8; 8]) -> Self {
+        Self {
+            time: u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            value: i16::from_ne_bytes([bytes[4], bytes[5]]),
+            type_: bytes[6],
+            number: bytes[7],
+        }
+    }
+
+    /// Whether this reports the state at open rather than a change.
+    pub fn is_init(&self) -> bool {
+        self.type_ & JS_EVENT_INIT != 0
+    }
+
+    /// The equivalent evdev event, given the axis and button maps of the
+    /// device.  `None` for indices outside the maps.
+    pub fn to_input_event(&self, axes: &[u8], buttons: &[u16]) -> Option<input_event> {
+        let (type_, code) = match self.type_ & !JS_EVENT_INIT {
+            JS_EVENT_BUTTON => (EV_KEY as u16, *buttons.get(self.number as usize)?),
+            JS_EVENT_AXIS => (EV_ABS as u16, *axes.get(self.number as usize)? as u16),
+            _ => return None,
+        };
+        // Safety: input_event is plain old data.
+        let mut event: input_event = unsafe { std::mem::zeroed() };
+        event.time.tv_sec = (self.time / 1000) as _;
+        event.time.tv_usec = (self.time % 1000 * 1000) as _;
+        (event.type_, event.code, event.value) = (type_, code, self.value.into());
+        Some(event)
+    }
+}
+
+/// An open joydev node.
+#[derive(Debug)]
+pub struct Joydev {
+    file: File,
+    axes: Vec<u8>,
+    buttons: Vec<u16>,
+    hat_state: HatState,
+    disconnected: bool,
+}
+
+impl Joydev {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_file(File::open(path)?)
+    }
+
+    /// Wrap an open node, querying its axis and button maps.
+    pub fn from_file(file: File) -> io::Result<Self> {
+        let fd = file.as_raw_fd();
+        let mut count = 0u8;
+        check(unsafe { convert_ioctl_res!(ioctl(fd, request_code_read!(b'j', 0x11, 1) as _, &mut count)) })?;
+        let mut axes = [0u8; ABS_CNT as usize];
+        check(unsafe { convert_ioctl_res!(ioctl(fd, request_code_read!(b'j', 0x32, axes.len()) as _, axes.as_mut_ptr())) })?;
+        let axes = axes[..count as usize].to_vec();
+
+        check(unsafe { convert_ioctl_res!(ioctl(fd, request_code_read!(b'j', 0x12, 1) as _, &mut count)) })?;
+        let mut buttons = [0u16; (KEY_MAX - BTN_MISC + 1) as usize];
+        let len = size_of::<u16>() * buttons.len();
+        check(unsafe { convert_ioctl_res!(ioctl(fd, request_code_read!(b'j', 0x34, len) as _, buttons.as_mut_ptr())) })?;
+        let buttons = buttons[..count as usize].to_vec();
+
+        Ok(Self::with_maps(file, axes, buttons))
+    }
+
+    /// Wrap an open file with known maps, e.g. a recording of `js_event`s.
+    pub fn with_maps(file: File, axes: Vec<u8>, buttons: Vec<u16>) -> Self {
+        Self { file, axes, buttons, hat_state: HatState::new(), disconnected: false }
+    }
+
+    /// `ABS_*` code of each axis index (`JSIOCGAXMAP`).
+    pub fn axis_map(&self) -> &[u8] {
+        &self.axes
+    }
+
+    /// `BTN_*` / `KEY_*` code of each button index (`JSIOCGBTNMAP`).
+    pub fn button_map(&self) -> &[u16] {
+        &self.buttons
+    }
+
+    /// Device name (`JSIOCGNAME`).
+    pub fn name(&self) -> io::Result<String> {
+        let mut buf = [0 as c_char; 256];
+        let request = request_code_read!(b'j', 0x13, buf.len());
+        check(unsafe { convert_ioctl_res!(ioctl(self.as_raw_fd(), request as _, buf.as_mut_ptr())) })?;
+        Ok(c_string(&buf))
+    }
+
+    /// Block until the next raw event is available.
+    pub fn read_js_event(&mut self) -> io::Result<JsEvent> {
+        let mut buf = [0; size_of::<JsEvent>()];
+        self.file.read_exact(&mut buf)?;
+        Ok(JsEvent::from_bytes(buf))
+    }
+
+    /// Block until the next decoded event, starting with the initial state
+    /// of every axis and button.  Axis values are in the range of
+    /// [`AXIS_INFO`].  Once the device goes away this returns
+    /// [`Event::Disconnect`] a single time, and errors afterwards.
+    pub fn next_event<T>(&mut self) -> io::Result<Event<T>> where T: TryFrom<i32> {
+        if self.disconnected {
+            return Err(io::Error::from_raw_os_error(nix::libc::ENODEV));
+        }
+        loop {
+            match self.read_js_event() {
+                Ok(event) => {
+                    let Some(raw) = event.to_input_event(&self.axes, &self.buttons) else {
+                        continue;
+                    };
+                    if let Some(event) = Event::from_input_event(&raw, &mut self.hat_state) {
+                        return Ok(event);
+                    }
+                }
+                Err(err) if is_disconnect(&err) => {
+                    self.disconnected = true;
+                    return Ok(Event::Disconnect);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+impl AsRawFd for Joydev {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Axis, Button, Direction, Hat};
+
+    const AXES: [u8; 3] = [ABS_X as u8, ABS_HAT0X as u8, ABS_HAT0Y as u8];
+    const BUTTONS: [u16; 2] = [BTN_SOUTH as u16, BTN_EAST as u16];
+
+    fn js(type_: u8, number: u8, value: i16) -> JsEvent {
+        JsEvent { time: 1234, value, type_, number }
+    }
+
+    #[test]
+    fn from_bytes_layout() {
+        let mut bytes = [0; 8];
+        bytes[..4].copy_from_slice(&0x0102_0304u32.to_ne_bytes());
+        bytes[4..6].copy_from_slice(&(-2i16).to_ne_bytes());
+        bytes[6] = JS_EVENT_AXIS | JS_EVENT_INIT;
+        bytes[7] = 5;
+        let event = JsEvent::from_bytes(bytes);
+        assert_eq!(event, JsEvent { time: 0x0102_0304, value: -2, type_: JS_EVENT_AXIS | JS_EVENT_INIT, number: 5 });
+        assert!(event.is_init());
+        assert_eq!(size_of::<JsEvent>(), 8);
+    }
+
+    #[test]
+    fn init_flag_is_stripped() {
+        let raw = js(JS_EVENT_BUTTON | JS_EVENT_INIT, 1, 1).to_input_event(&AXES, &BUTTONS).unwrap();
+        assert_eq!((raw.type_, raw.code, raw.value), (EV_KEY as u16, BTN_EAST as u16, 1));
+        assert_eq!((raw.time.tv_sec, raw.time.tv_usec), (1, 234_000));
+        assert!(!js(JS_EVENT_BUTTON, 1, 1).is_init());
+    }
+
+    #[test]
+    fn out_of_map_indices() {
+        assert!(js(JS_EVENT_AXIS, 3, 0).to_input_event(&AXES, &BUTTONS).is_none());
+        assert!(js(JS_EVENT_BUTTON, 2, 1).to_input_event(&AXES, &BUTTONS).is_none());
+        assert!(js(0x04, 0, 1).to_input_event(&AXES, &BUTTONS).is_none());
+    }
+
+    #[test]
+    fn decodes_like_evdev() {
+        let cases = [
+            (js(JS_EVENT_AXIS, 0, -32767), EV_ABS, ABS_X, Event::Axis(Axis::JoyX, -32767)),
+            (js(JS_EVENT_AXIS, 1, 32767), EV_ABS, ABS_HAT0X, Event::Hat(Hat::Pov, Direction::Right)),
+            (js(JS_EVENT_BUTTON | JS_EVENT_INIT, 0, 1), EV_KEY, BTN_SOUTH, Event::Button(Button::ActionA, true)),
+        ];
+        for (event, type_, code, expected) in cases {
+            let raw = event.to_input_event(&AXES, &BUTTONS).unwrap();
+            assert_eq!((raw.type_, raw.code), (type_ as u16, code as u16));
+            let evdev = input_event { time: timeval { tv_sec: 0, tv_usec: 0 }, type_: type_ as u16, code: code as u16, value: event.value.into() };
+            assert_eq!(Event::<i32>::from_input_event(&raw, &mut HatState::new()), Some(expected));
+            assert_eq!(Event::<i32>::from_input_event(&evdev, &mut HatState::new()), Some(expected));
+        }
+    }
+}
@@ -0,0 +1,693 @@
+//! Payload-free identification of [flat](crate::flat) [`Event`] variants.
+
+use input_linux_sys::*;
+
+use crate::flat::Event;
+use crate::{ABS_UNKNOWN0, ABS_UNKNOWN1, ABS_UNKNOWN2, ABS_UNKNOWN3, ABS_UNKNOWN4};
+
+/// Which [`Event`] variant an event is, without its value, e.g. to name
+/// "the `ActionA` button" or "the `JoyX` axis".
+///
+/// `Number` stands for all numbered buttons; the number is part of the
+/// value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventKind {
+    Disconnect,
+    Exit,
+    ActionA,
+    ActionB,
+    ActionC,
+    ActionH,
+    ActionV,
+    ActionD,
+    MenuL,
+    MenuR,
+    Joy,
+    Cam,
+    BumperL,
+    BumperR,
+    TriggerL,
+    TriggerR,
+    Up,
+    Down,
+    Left,
+    Right,
+    PovUp,
+    PovDown,
+    PovLeft,
+    PovRight,
+    HatUp,
+    HatDown,
+    HatLeft,
+    HatRight,
+    TrimUp,
+    TrimDown,
+    TrimLeft,
+    TrimRight,
+    MicUp,
+    MicDown,
+    MicLeft,
+    MicRight,
+    JoyX,
+    JoyY,
+    JoyZ,
+    CamX,
+    CamY,
+    CamZ,
+    Slew,
+    Throttle,
+    ThrottleL,
+    ThrottleR,
+    Volume,
+    Wheel,
+    Rudder,
+    Gas,
+    Brake,
+    MicPush,
+    Trigger,
+    Bumper,
+    ActionM,
+    ActionL,
+    ActionR,
+    Pinky,
+    PinkyForward,
+    PinkyBackward,
+    FlapsUp,
+    FlapsDown,
+    BoatForward,
+    BoatBackward,
+    AutopilotPath,
+    AutopilotAlt,
+    EngineMotorL,
+    EngineMotorR,
+    EngineFuelFlowL,
+    EngineFuelFlowR,
+    EngineIgnitionL,
+    EngineIgnitionR,
+    SpeedbrakeBackward,
+    SpeedbrakeForward,
+    ChinaBackward,
+    ChinaForward,
+    Apu,
+    RadarAltimeter,
+    LandingGearSilence,
+    Eac,
+    AutopilotToggle,
+    ThrottleButton,
+    MouseX,
+    MouseY,
+    Mouse,
+    Number,
+    PaddleLeft,
+    PaddleRight,
+    PinkyLeft,
+    PinkyRight,
+    Context,
+    Dpi,
+    ScrollX,
+    ScrollY,
+    Scroll,
+    ActionWheelX,
+    ActionWheelY,
+    Repeat,
+    RepeatDelay,
+    RepeatPeriod,
+    Power,
+    Click,
+    Bell,
+    Tone,
+}
+
+impl EventKind {
+    /// Every kind, in declaration order.
+    pub const ALL: [EventKind; 104] = [
+        EventKind::Disconnect, EventKind::Exit, EventKind::ActionA, EventKind::ActionB, EventKind::ActionC,
+        EventKind::ActionH, EventKind::ActionV, EventKind::ActionD, EventKind::MenuL, EventKind::MenuR,
+        EventKind::Joy, EventKind::Cam, EventKind::BumperL, EventKind::BumperR, EventKind::TriggerL,
+        EventKind::TriggerR, EventKind::Up, EventKind::Down, EventKind::Left, EventKind::Right,
+        EventKind::PovUp, EventKind::PovDown, EventKind::PovLeft, EventKind::PovRight, EventKind::HatUp,
+        EventKind::HatDown, EventKind::HatLeft, EventKind::HatRight, EventKind::TrimUp, EventKind::TrimDown,
+        EventKind::TrimLeft, EventKind::TrimRight, EventKind::MicUp, EventKind::MicDown, EventKind::MicLeft,
+        EventKind::MicRight, EventKind::JoyX, EventKind::JoyY, EventKind::JoyZ, EventKind::CamX,
+        EventKind::CamY, EventKind::CamZ, EventKind::Slew, EventKind::Throttle, EventKind::ThrottleL,
+        EventKind::ThrottleR, EventKind::Volume, EventKind::Wheel, EventKind::Rudder, EventKind::Gas,
+        EventKind::Brake, EventKind::MicPush, EventKind::Trigger, EventKind::Bumper, EventKind::ActionM,
+        EventKind::ActionL, EventKind::ActionR, EventKind::Pinky, EventKind::PinkyForward, EventKind::PinkyBackward,
+        EventKind::FlapsUp, EventKind::FlapsDown, EventKind::BoatForward, EventKind::BoatBackward, EventKind::AutopilotPath,
+        EventKind::AutopilotAlt, EventKind::EngineMotorL, EventKind::EngineMotorR, EventKind::EngineFuelFlowL, EventKind::EngineFuelFlowR,
+        EventKind::EngineIgnitionL, EventKind::EngineIgnitionR, EventKind::SpeedbrakeBackward, EventKind::SpeedbrakeForward, EventKind::ChinaBackward,
+        EventKind::ChinaForward, EventKind::Apu, EventKind::RadarAltimeter, EventKind::LandingGearSilence, EventKind::Eac,
+        EventKind::AutopilotToggle, EventKind::ThrottleButton, EventKind::MouseX, EventKind::MouseY, EventKind::Mouse,
+        EventKind::Number, EventKind::PaddleLeft, EventKind::PaddleRight, EventKind::PinkyLeft, EventKind::PinkyRight,
+        EventKind::Context, EventKind::Dpi, EventKind::ScrollX, EventKind::ScrollY, EventKind::Scroll,
+        EventKind::ActionWheelX, EventKind::ActionWheelY, EventKind::Repeat, EventKind::RepeatDelay, EventKind::RepeatPeriod,
+        EventKind::Power, EventKind::Click, EventKind::Bell, EventKind::Tone,
+    ];
+
+    /// Two-state buttons and switches, carrying `bool`.
+    pub fn is_button(self) -> bool {
+        matches!(self,
+            EventKind::Exit | EventKind::ActionA | EventKind::ActionB | EventKind::ActionC
+            | EventKind::ActionH | EventKind::ActionV | EventKind::ActionD | EventKind::MenuL
+            | EventKind::MenuR | EventKind::Joy | EventKind::Cam | EventKind::BumperL
+            | EventKind::BumperR | EventKind::Up | EventKind::Down | EventKind::Left
+            | EventKind::Right | EventKind::PovUp | EventKind::PovDown | EventKind::PovLeft
+            | EventKind::PovRight | EventKind::HatUp | EventKind::HatDown | EventKind::HatLeft
+            | EventKind::HatRight | EventKind::TrimUp | EventKind::TrimDown | EventKind::TrimLeft
+            | EventKind::TrimRight | EventKind::MicUp | EventKind::MicDown | EventKind::MicLeft
+            | EventKind::MicRight | EventKind::MicPush | EventKind::Trigger | EventKind::Bumper
+            | EventKind::ActionM | EventKind::ActionL | EventKind::ActionR | EventKind::Pinky
+            | EventKind::PinkyForward | EventKind::PinkyBackward | EventKind::FlapsUp | EventKind::FlapsDown
+            | EventKind::BoatForward | EventKind::BoatBackward | EventKind::AutopilotPath | EventKind::AutopilotAlt
+            | EventKind::EngineMotorL | EventKind::EngineMotorR | EventKind::EngineFuelFlowL | EventKind::EngineFuelFlowR
+            | EventKind::EngineIgnitionL | EventKind::EngineIgnitionR | EventKind::SpeedbrakeBackward | EventKind::SpeedbrakeForward
+            | EventKind::ChinaBackward | EventKind::ChinaForward | EventKind::Apu | EventKind::RadarAltimeter
+            | EventKind::LandingGearSilence | EventKind::Eac | EventKind::AutopilotToggle | EventKind::ThrottleButton
+            | EventKind::Mouse | EventKind::Number | EventKind::PaddleLeft | EventKind::PaddleRight
+            | EventKind::PinkyLeft | EventKind::PinkyRight | EventKind::Context | EventKind::Dpi
+            | EventKind::Scroll
+        )
+    }
+
+    /// Analog axes, carrying `T`.
+    pub fn is_axis(self) -> bool {
+        matches!(self,
+            EventKind::TriggerL | EventKind::TriggerR | EventKind::JoyX | EventKind::JoyY
+            | EventKind::JoyZ | EventKind::CamX | EventKind::CamY | EventKind::CamZ
+            | EventKind::Slew | EventKind::Throttle | EventKind::ThrottleL | EventKind::ThrottleR
+            | EventKind::Volume | EventKind::Wheel | EventKind::Rudder | EventKind::Gas
+            | EventKind::Brake | EventKind::MouseX | EventKind::MouseY | EventKind::ScrollX
+            | EventKind::ScrollY | EventKind::ActionWheelX | EventKind::ActionWheelY
+        )
+    }
+
+    /// One direction of a hat decoded from an `ABS_HAT*` axis pair.  These
+    /// are buttons as well.
+    pub fn is_hat_direction(self) -> bool {
+        matches!(self,
+            EventKind::PovUp | EventKind::PovDown | EventKind::PovLeft | EventKind::PovRight
+            | EventKind::HatUp | EventKind::HatDown | EventKind::HatLeft | EventKind::HatRight
+            | EventKind::TrimUp | EventKind::TrimDown | EventKind::TrimLeft | EventKind::TrimRight
+            | EventKind::MicUp | EventKind::MicDown | EventKind::MicLeft | EventKind::MicRight
+        )
+    }
+
+    /// Name for showing to users, e.g. `"Left Bumper"`.
+    pub fn label(self) -> &'static str {
+        match self {
+            EventKind::Disconnect => "Disconnect",
+            EventKind::Exit => "Exit",
+            EventKind::ActionA => "Action A",
+            EventKind::ActionB => "Action B",
+            EventKind::ActionC => "Action C",
+            EventKind::ActionH => "Action H",
+            EventKind::ActionV => "Action V",
+            EventKind::ActionD => "Action D",
+            EventKind::MenuL => "Left Menu",
+            EventKind::MenuR => "Right Menu",
+            EventKind::Joy => "Joystick Push",
+            EventKind::Cam => "Camera Stick Push",
+            EventKind::BumperL => "Left Bumper",
+            EventKind::BumperR => "Right Bumper",
+            EventKind::TriggerL => "Left Trigger",
+            EventKind::TriggerR => "Right Trigger",
+            EventKind::Up => "D-Pad Up",
+            EventKind::Down => "D-Pad Down",
+            EventKind::Left => "D-Pad Left",
+            EventKind::Right => "D-Pad Right",
+            EventKind::PovUp => "POV Hat Up",
+            EventKind::PovDown => "POV Hat Down",
+            EventKind::PovLeft => "POV Hat Left",
+            EventKind::PovRight => "POV Hat Right",
+            EventKind::HatUp => "Extra Hat Up",
+            EventKind::HatDown => "Extra Hat Down",
+            EventKind::HatLeft => "Extra Hat Left",
+            EventKind::HatRight => "Extra Hat Right",
+            EventKind::TrimUp => "Trim Hat Up",
+            EventKind::TrimDown => "Trim Hat Down",
+            EventKind::TrimLeft => "Trim Hat Left",
+            EventKind::TrimRight => "Trim Hat Right",
+            EventKind::MicUp => "Mic Hat Up",
+            EventKind::MicDown => "Mic Hat Down",
+            EventKind::MicLeft => "Mic Hat Left",
+            EventKind::MicRight => "Mic Hat Right",
+            EventKind::JoyX => "Joystick X",
+            EventKind::JoyY => "Joystick Y",
+            EventKind::JoyZ => "Joystick Z",
+            EventKind::CamX => "Camera Stick X",
+            EventKind::CamY => "Camera Stick Y",
+            EventKind::CamZ => "Camera Stick Z",
+            EventKind::Slew => "Slew",
+            EventKind::Throttle => "Throttle",
+            EventKind::ThrottleL => "Left Throttle",
+            EventKind::ThrottleR => "Right Throttle",
+            EventKind::Volume => "Volume",
+            EventKind::Wheel => "Wheel",
+            EventKind::Rudder => "Rudder",
+            EventKind::Gas => "Gas Pedal",
+            EventKind::Brake => "Brake Pedal",
+            EventKind::MicPush => "Mic Hat Push",
+            EventKind::Trigger => "Trigger",
+            EventKind::Bumper => "Side Bumper",
+            EventKind::ActionM => "Action M",
+            EventKind::ActionL => "Action L",
+            EventKind::ActionR => "Action R",
+            EventKind::Pinky => "Pinky",
+            EventKind::PinkyForward => "Pinky Switch Forward",
+            EventKind::PinkyBackward => "Pinky Switch Backward",
+            EventKind::FlapsUp => "Flaps Up",
+            EventKind::FlapsDown => "Flaps Down",
+            EventKind::BoatForward => "Boat Switch Forward",
+            EventKind::BoatBackward => "Boat Switch Backward",
+            EventKind::AutopilotPath => "Autopilot Path",
+            EventKind::AutopilotAlt => "Autopilot Alt",
+            EventKind::EngineMotorL => "Left Engine Motor",
+            EventKind::EngineMotorR => "Right Engine Motor",
+            EventKind::EngineFuelFlowL => "Left Engine Fuel Flow",
+            EventKind::EngineFuelFlowR => "Right Engine Fuel Flow",
+            EventKind::EngineIgnitionL => "Left Engine Ignition",
+            EventKind::EngineIgnitionR => "Right Engine Ignition",
+            EventKind::SpeedbrakeBackward => "Speedbrake Backward",
+            EventKind::SpeedbrakeForward => "Speedbrake Forward",
+            EventKind::ChinaBackward => "China Hat Backward",
+            EventKind::ChinaForward => "China Hat Forward",
+            EventKind::Apu => "APU",
+            EventKind::RadarAltimeter => "Radar Altimeter",
+            EventKind::LandingGearSilence => "Landing Gear Silence",
+            EventKind::Eac => "EAC",
+            EventKind::AutopilotToggle => "Autopilot Toggle",
+            EventKind::ThrottleButton => "Throttle Button",
+            EventKind::MouseX => "Mouse X",
+            EventKind::MouseY => "Mouse Y",
+            EventKind::Mouse => "Mouse Button",
+            EventKind::Number => "Numbered Button",
+            EventKind::PaddleLeft => "Left Paddle",
+            EventKind::PaddleRight => "Right Paddle",
+            EventKind::PinkyLeft => "Left Pinky",
+            EventKind::PinkyRight => "Right Pinky",
+            EventKind::Context => "Context Menu",
+            EventKind::Dpi => "DPI",
+            EventKind::ScrollX => "Scroll X",
+            EventKind::ScrollY => "Scroll Y",
+            EventKind::Scroll => "Scroll Button",
+            EventKind::ActionWheelX => "Action Wheel X",
+            EventKind::ActionWheelY => "Action Wheel Y",
+            EventKind::Repeat => "Key Repeat",
+            EventKind::RepeatDelay => "Repeat Delay",
+            EventKind::RepeatPeriod => "Repeat Period",
+            EventKind::Power => "Power",
+            EventKind::Click => "Click",
+            EventKind::Bell => "Bell",
+            EventKind::Tone => "Tone",
+        }
+    }
+
+    /// `(type, code)` pairs that decode to this kind.  Empty for kinds not
+    /// produced by decoding, like the HOTAS switches or
+    /// [`EventKind::Disconnect`], and for [`EventKind::Repeat`] and
+    /// [`EventKind::Power`], which are decoded from any key or power code
+    /// and carry it in their value.
+    pub fn codes(self) -> &'static [(u16, u16)] {
+        match self {
+            EventKind::Exit => &[(KEY, BTN_MODE as u16)],
+            EventKind::ActionA => &[(KEY, BTN_SOUTH as u16)],
+            EventKind::ActionB => &[(KEY, BTN_EAST as u16)],
+            EventKind::ActionC => &[(KEY, BTN_C as u16)],
+            EventKind::ActionH => &[(KEY, BTN_WEST as u16)],
+            EventKind::ActionV => &[(KEY, BTN_NORTH as u16)],
+            EventKind::ActionD => &[(KEY, BTN_Z as u16)],
+            EventKind::MenuL => &[(KEY, BTN_SELECT as u16)],
+            EventKind::MenuR => &[(KEY, BTN_START as u16)],
+            EventKind::Joy => &[(KEY, BTN_THUMBL as u16)],
+            EventKind::Cam => &[(KEY, BTN_THUMBR as u16)],
+            EventKind::BumperL => &[(KEY, BTN_TL as u16)],
+            EventKind::BumperR => &[(KEY, BTN_TR as u16)],
+            EventKind::TriggerL => &[(KEY, BTN_TL2 as u16)],
+            EventKind::TriggerR => &[(KEY, BTN_TR2 as u16)],
+            EventKind::Up => &[(KEY, BTN_DPAD_UP as u16)],
+            EventKind::Down => &[(KEY, BTN_DPAD_DOWN as u16)],
+            EventKind::Left => &[(KEY, BTN_DPAD_LEFT as u16)],
+            EventKind::Right => &[(KEY, BTN_DPAD_RIGHT as u16)],
+            EventKind::PovUp => &[(ABS, ABS_HAT0Y as u16)],
+            EventKind::PovDown => &[(ABS, ABS_HAT0Y as u16)],
+            EventKind::PovLeft => &[(ABS, ABS_HAT0X as u16)],
+            EventKind::PovRight => &[(ABS, ABS_HAT0X as u16)],
+            EventKind::HatUp => &[(ABS, ABS_HAT1Y as u16)],
+            EventKind::HatDown => &[(ABS, ABS_HAT1Y as u16)],
+            EventKind::HatLeft => &[(ABS, ABS_HAT1X as u16)],
+            EventKind::HatRight => &[(ABS, ABS_HAT1X as u16)],
+            EventKind::TrimUp => &[(ABS, ABS_HAT2Y as u16)],
+            EventKind::TrimDown => &[(ABS, ABS_HAT2Y as u16)],
+            EventKind::TrimLeft => &[(ABS, ABS_HAT2X as u16)],
+            EventKind::TrimRight => &[(ABS, ABS_HAT2X as u16)],
+            EventKind::MicUp => &[(ABS, ABS_HAT3Y as u16)],
+            EventKind::MicDown => &[(ABS, ABS_HAT3Y as u16)],
+            EventKind::MicLeft => &[(ABS, ABS_HAT3X as u16)],
+            EventKind::MicRight => &[(ABS, ABS_HAT3X as u16)],
+            EventKind::JoyX => &[(ABS, ABS_X as u16)],
+            EventKind::JoyY => &[(ABS, ABS_Y as u16)],
+            EventKind::JoyZ => &[(ABS, ABS_Z as u16)],
+            EventKind::CamX => &[(ABS, ABS_RX as u16)],
+            EventKind::CamY => &[(ABS, ABS_RY as u16)],
+            EventKind::CamZ => &[(ABS, ABS_RZ as u16)],
+            EventKind::Slew => &[(ABS, ABS_UNKNOWN0 as u16)],
+            EventKind::Throttle => &[(ABS, ABS_THROTTLE as u16)],
+            EventKind::ThrottleL => &[(ABS, ABS_UNKNOWN1 as u16)],
+            EventKind::ThrottleR => &[(ABS, ABS_UNKNOWN2 as u16)],
+            EventKind::Wheel => &[(ABS, ABS_WHEEL as u16)],
+            EventKind::Rudder => &[(ABS, ABS_RUDDER as u16)],
+            EventKind::Gas => &[(ABS, ABS_GAS as u16)],
+            EventKind::Brake => &[(ABS, ABS_BRAKE as u16)],
+            EventKind::Trigger => &[(KEY, BTN_TRIGGER as u16)],
+            EventKind::Bumper => &[(KEY, BTN_THUMB2 as u16)],
+            EventKind::ActionM => &[(KEY, BTN_THUMB as u16)],
+            EventKind::ActionL => &[(KEY, BTN_TOP2 as u16)],
+            EventKind::ActionR => &[(KEY, BTN_TOP as u16)],
+            EventKind::Pinky => &[(KEY, BTN_PINKIE as u16)],
+            EventKind::MouseX => &[(REL, REL_X as u16)],
+            EventKind::MouseY => &[(REL, REL_Y as u16)],
+            EventKind::PaddleLeft => &[(KEY, KEY_BACK as u16)],
+            EventKind::PaddleRight => &[(KEY, KEY_FORWARD as u16)],
+            EventKind::PinkyLeft => &[(KEY, 0x140)],
+            EventKind::PinkyRight => &[(KEY, 0x13F)],
+            EventKind::Context => &[(KEY, KEY_MENU as u16)],
+            EventKind::ScrollX => &[(ABS, ABS_UNKNOWN3 as u16)],
+            EventKind::ScrollY => &[(ABS, ABS_UNKNOWN4 as u16)],
+            EventKind::RepeatDelay => &[(REP, REP_DELAY as u16)],
+            EventKind::RepeatPeriod => &[(REP, REP_PERIOD as u16)],
+            EventKind::Click => &[(SND, SND_CLICK as u16)],
+            EventKind::Bell => &[(SND, SND_BELL as u16)],
+            EventKind::Tone => &[(SND, SND_TONE as u16)],
+            EventKind::Number => &NUMBER_CODES,
+            _ => &[],
+        }
+    }
+}
+
+const KEY: u16 = EV_KEY as u16;
+const ABS: u16 = EV_ABS as u16;
+const REL: u16 = EV_REL as u16;
+const REP: u16 = EV_REP as u16;
+const SND: u16 = EV_SND as u16;
+
+/// Codes of `Number(1)` to `Number(50)`: `BTN_BASE` .. `BTN_BASE` + 9,
+/// then `BTN_TRIGGER_HAPPY1` .. `BTN_TRIGGER_HAPPY40`.
+const NUMBER_CODES: [(u16, u16); 50] = {
+    let mut codes = [(0, 0); 50];
+    let mut i = 0;
+    while i < 50 {
+        let code = if i < 10 { BTN_BASE + i as i32 } else { BTN_TRIGGER_HAPPY1 + i as i32 - 10 };
+        codes[i] = (KEY, code as u16);
+        i += 1;
+    }
+    codes
+};
+
+impl<T> Event<T> where T: TryFrom<i32> {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Disconnect => EventKind::Disconnect,
+            Event::Exit(..) => EventKind::Exit,
+            Event::ActionA(..) => EventKind::ActionA,
+            Event::ActionB(..) => EventKind::ActionB,
+            Event::ActionC(..) => EventKind::ActionC,
+            Event::ActionH(..) => EventKind::ActionH,
+            Event::ActionV(..) => EventKind::ActionV,
+            Event::ActionD(..) => EventKind::ActionD,
+            Event::MenuL(..) => EventKind::MenuL,
+            Event::MenuR(..) => EventKind::MenuR,
+            Event::Joy(..) => EventKind::Joy,
+            Event::Cam(..) => EventKind::Cam,
+            Event::BumperL(..) => EventKind::BumperL,
+            Event::BumperR(..) => EventKind::BumperR,
+            Event::TriggerL(..) => EventKind::TriggerL,
+            Event::TriggerR(..) => EventKind::TriggerR,
+            Event::Up(..) => EventKind::Up,
+            Event::Down(..) => EventKind::Down,
+            Event::Left(..) => EventKind::Left,
+            Event::Right(..) => EventKind::Right,
+            Event::PovUp(..) => EventKind::PovUp,
+            Event::PovDown(..) => EventKind::PovDown,
+            Event::PovLeft(..) => EventKind::PovLeft,
+            Event::PovRight(..) => EventKind::PovRight,
+            Event::HatUp(..) => EventKind::HatUp,
+            Event::HatDown(..) => EventKind::HatDown,
+            Event::HatLeft(..) => EventKind::HatLeft,
+            Event::HatRight(..) => EventKind::HatRight,
+            Event::TrimUp(..) => EventKind::TrimUp,
+            Event::TrimDown(..) => EventKind::TrimDown,
+            Event::TrimLeft(..) => EventKind::TrimLeft,
+            Event::TrimRight(..) => EventKind::TrimRight,
+            Event::MicUp(..) => EventKind::MicUp,
+            Event::MicDown(..) => EventKind::MicDown,
+            Event::MicLeft(..) => EventKind::MicLeft,
+            Event::MicRight(..) => EventKind::MicRight,
+            Event::JoyX(..) => EventKind::JoyX,
+            Event::JoyY(..) => EventKind::JoyY,
+            Event::JoyZ(..) => EventKind::JoyZ,
+            Event::CamX(..) => EventKind::CamX,
+            Event::CamY(..) => EventKind::CamY,
+            Event::CamZ(..) => EventKind::CamZ,
+            Event::Slew(..) => EventKind::Slew,
+            Event::Throttle(..) => EventKind::Throttle,
+            Event::ThrottleL(..) => EventKind::ThrottleL,
+            Event::ThrottleR(..) => EventKind::ThrottleR,
+            Event::Volume(..) => EventKind::Volume,
+            Event::Wheel(..) => EventKind::Wheel,
+            Event::Rudder(..) => EventKind::Rudder,
+            Event::Gas(..) => EventKind::Gas,
+            Event::Brake(..) => EventKind::Brake,
+            Event::MicPush(..) => EventKind::MicPush,
+            Event::Trigger(..) => EventKind::Trigger,
+            Event::Bumper(..) => EventKind::Bumper,
+            Event::ActionM(..) => EventKind::ActionM,
+            Event::ActionL(..) => EventKind::ActionL,
+            Event::ActionR(..) => EventKind::ActionR,
+            Event::Pinky(..) => EventKind::Pinky,
+            Event::PinkyForward(..) => EventKind::PinkyForward,
+            Event::PinkyBackward(..) => EventKind::PinkyBackward,
+            Event::FlapsUp(..) => EventKind::FlapsUp,
+            Event::FlapsDown(..) => EventKind::FlapsDown,
+            Event::BoatForward(..) => EventKind::BoatForward,
+            Event::BoatBackward(..) => EventKind::BoatBackward,
+            Event::AutopilotPath(..) => EventKind::AutopilotPath,
+            Event::AutopilotAlt(..) => EventKind::AutopilotAlt,
+            Event::EngineMotorL(..) => EventKind::EngineMotorL,
+            Event::EngineMotorR(..) => EventKind::EngineMotorR,
+            Event::EngineFuelFlowL(..) => EventKind::EngineFuelFlowL,
+            Event::EngineFuelFlowR(..) => EventKind::EngineFuelFlowR,
+            Event::EngineIgnitionL(..) => EventKind::EngineIgnitionL,
+            Event::EngineIgnitionR(..) => EventKind::EngineIgnitionR,
+            Event::SpeedbrakeBackward(..) => EventKind::SpeedbrakeBackward,
+            Event::SpeedbrakeForward(..) => EventKind::SpeedbrakeForward,
+            Event::ChinaBackward(..) => EventKind::ChinaBackward,
+            Event::ChinaForward(..) => EventKind::ChinaForward,
+            Event::Apu(..) => EventKind::Apu,
+            Event::RadarAltimeter(..) => EventKind::RadarAltimeter,
+            Event::LandingGearSilence(..) => EventKind::LandingGearSilence,
+            Event::Eac(..) => EventKind::Eac,
+            Event::AutopilotToggle(..) => EventKind::AutopilotToggle,
+            Event::ThrottleButton(..) => EventKind::ThrottleButton,
+            Event::MouseX(..) => EventKind::MouseX,
+            Event::MouseY(..) => EventKind::MouseY,
+            Event::Mouse(..) => EventKind::Mouse,
+            Event::Number(..) => EventKind::Number,
+            Event::PaddleLeft(..) => EventKind::PaddleLeft,
+            Event::PaddleRight(..) => EventKind::PaddleRight,
+            Event::PinkyLeft(..) => EventKind::PinkyLeft,
+            Event::PinkyRight(..) => EventKind::PinkyRight,
+            Event::Context(..) => EventKind::Context,
+            Event::Dpi(..) => EventKind::Dpi,
+            Event::ScrollX(..) => EventKind::ScrollX,
+            Event::ScrollY(..) => EventKind::ScrollY,
+            Event::Scroll(..) => EventKind::Scroll,
+            Event::ActionWheelX(..) => EventKind::ActionWheelX,
+            Event::ActionWheelY(..) => EventKind::ActionWheelY,
+            Event::Repeat(..) => EventKind::Repeat,
+            Event::RepeatDelay(..) => EventKind::RepeatDelay,
+            Event::RepeatPeriod(..) => EventKind::RepeatPeriod,
+            Event::Power(..) => EventKind::Power,
+            Event::Click(..) => EventKind::Click,
+            Event::Bell(..) => EventKind::Bell,
+            Event::Tone(..) => EventKind::Tone,
+        }
+    }
+}
+
+impl<T> From<&Event<T>> for EventKind where T: TryFrom<i32> {
+    fn from(event: &Event<T>) -> Self {
+        event.kind()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flat::Converter;
+
+    /// An event of each kind.  The match has no wildcard, so a new kind
+    /// doesn't compile until it is added here.
+    fn sample(kind: EventKind) -> Event {
+        match kind {
+            EventKind::Disconnect => Event::Disconnect,
+            EventKind::Exit => Event::Exit(true),
+            EventKind::ActionA => Event::ActionA(true),
+            EventKind::ActionB => Event::ActionB(true),
+            EventKind::ActionC => Event::ActionC(true),
+            EventKind::ActionH => Event::ActionH(true),
+            EventKind::ActionV => Event::ActionV(true),
+            EventKind::ActionD => Event::ActionD(true),
+            EventKind::MenuL => Event::MenuL(true),
+            EventKind::MenuR => Event::MenuR(true),
+            EventKind::Joy => Event::Joy(true),
+            EventKind::Cam => Event::Cam(true),
+            EventKind::BumperL => Event::BumperL(true),
+            EventKind::BumperR => Event::BumperR(true),
+            EventKind::TriggerL => Event::TriggerL(7),
+            EventKind::TriggerR => Event::TriggerR(7),
+            EventKind::Up => Event::Up(true),
+            EventKind::Down => Event::Down(true),
+            EventKind::Left => Event::Left(true),
+            EventKind::Right => Event::Right(true),
+            EventKind::PovUp => Event::PovUp(true),
+            EventKind::PovDown => Event::PovDown(true),
+            EventKind::PovLeft => Event::PovLeft(true),
+            EventKind::PovRight => Event::PovRight(true),
+            EventKind::HatUp => Event::HatUp(true),
+            EventKind::HatDown => Event::HatDown(true),
+            EventKind::HatLeft => Event::HatLeft(true),
+            EventKind::HatRight => Event::HatRight(true),
+            EventKind::TrimUp => Event::TrimUp(true),
+            EventKind::TrimDown => Event::TrimDown(true),
+            EventKind::TrimLeft => Event::TrimLeft(true),
+            EventKind::TrimRight => Event::TrimRight(true),
+            EventKind::MicUp => Event::MicUp(true),
+            EventKind::MicDown => Event::MicDown(true),
+            EventKind::MicLeft => Event::MicLeft(true),
+            EventKind::MicRight => Event::MicRight(true),
+            EventKind::JoyX => Event::JoyX(7),
+            EventKind::JoyY => Event::JoyY(7),
+            EventKind::JoyZ => Event::JoyZ(7),
+            EventKind::CamX => Event::CamX(7),
+            EventKind::CamY => Event::CamY(7),
+            EventKind::CamZ => Event::CamZ(7),
+            EventKind::Slew => Event::Slew(7),
+            EventKind::Throttle => Event::Throttle(7),
+            EventKind::ThrottleL => Event::ThrottleL(7),
+            EventKind::ThrottleR => Event::ThrottleR(7),
+            EventKind::Volume => Event::Volume(7),
+            EventKind::Wheel => Event::Wheel(7),
+            EventKind::Rudder => Event::Rudder(7),
+            EventKind::Gas => Event::Gas(7),
+            EventKind::Brake => Event::Brake(7),
+            EventKind::MicPush => Event::MicPush(true),
+            EventKind::Trigger => Event::Trigger(true),
+            EventKind::Bumper => Event::Bumper(true),
+            EventKind::ActionM => Event::ActionM(true),
+            EventKind::ActionL => Event::ActionL(true),
+            EventKind::ActionR => Event::ActionR(true),
+            EventKind::Pinky => Event::Pinky(true),
+            EventKind::PinkyForward => Event::PinkyForward(true),
+            EventKind::PinkyBackward => Event::PinkyBackward(true),
+            EventKind::FlapsUp => Event::FlapsUp(true),
+            EventKind::FlapsDown => Event::FlapsDown(true),
+            EventKind::BoatForward => Event::BoatForward(true),
+            EventKind::BoatBackward => Event::BoatBackward(true),
+            EventKind::AutopilotPath => Event::AutopilotPath(true),
+            EventKind::AutopilotAlt => Event::AutopilotAlt(true),
+            EventKind::EngineMotorL => Event::EngineMotorL(true),
+            EventKind::EngineMotorR => Event::EngineMotorR(true),
+            EventKind::EngineFuelFlowL => Event::EngineFuelFlowL(true),
+            EventKind::EngineFuelFlowR => Event::EngineFuelFlowR(true),
+            EventKind::EngineIgnitionL => Event::EngineIgnitionL(true),
+            EventKind::EngineIgnitionR => Event::EngineIgnitionR(true),
+            EventKind::SpeedbrakeBackward => Event::SpeedbrakeBackward(true),
+            EventKind::SpeedbrakeForward => Event::SpeedbrakeForward(true),
+            EventKind::ChinaBackward => Event::ChinaBackward(true),
+            EventKind::ChinaForward => Event::ChinaForward(true),
+            EventKind::Apu => Event::Apu(true),
+            EventKind::RadarAltimeter => Event::RadarAltimeter(true),
+            EventKind::LandingGearSilence => Event::LandingGearSilence(true),
+            EventKind::Eac => Event::Eac(true),
+            EventKind::AutopilotToggle => Event::AutopilotToggle(true),
+            EventKind::ThrottleButton => Event::ThrottleButton(true),
+            EventKind::MouseX => Event::MouseX(7),
+            EventKind::MouseY => Event::MouseY(7),
+            EventKind::Mouse => Event::Mouse(true),
+            EventKind::Number => Event::Number(3, true),
+            EventKind::PaddleLeft => Event::PaddleLeft(true),
+            EventKind::PaddleRight => Event::PaddleRight(true),
+            EventKind::PinkyLeft => Event::PinkyLeft(true),
+            EventKind::PinkyRight => Event::PinkyRight(true),
+            EventKind::Context => Event::Context(true),
+            EventKind::Dpi => Event::Dpi(true),
+            EventKind::ScrollX => Event::ScrollX(7),
+            EventKind::ScrollY => Event::ScrollY(7),
+            EventKind::Scroll => Event::Scroll(true),
+            EventKind::ActionWheelX => Event::ActionWheelX(7),
+            EventKind::ActionWheelY => Event::ActionWheelY(7),
+            EventKind::Repeat => Event::Repeat(30),
+            EventKind::RepeatDelay => Event::RepeatDelay(7),
+            EventKind::RepeatPeriod => Event::RepeatPeriod(7),
+            EventKind::Power => Event::Power(0x74, 1),
+            EventKind::Click => Event::Click(true),
+            EventKind::Bell => Event::Bell(true),
+            EventKind::Tone => Event::Tone(7),
+        }
+    }
+
+    fn raw(type_: u16, code: u16, value: i32) -> input_event {
+        input_event { time: timeval { tv_sec: 0, tv_usec: 0 }, type_, code, value }
+    }
+
+    #[test]
+    fn all_is_every_kind_in_order() {
+        for (index, kind) in EventKind::ALL.into_iter().enumerate() {
+            assert_eq!(kind as usize, index, "{kind:?}");
+        }
+        assert_eq!(EventKind::ALL.len(), EventKind::Tone as usize + 1);
+    }
+
+    #[test]
+    fn kind_agrees_with_all() {
+        for kind in EventKind::ALL {
+            assert_eq!(sample(kind).kind(), kind);
+            assert_eq!(EventKind::from(&sample(kind)), kind);
+        }
+    }
+
+    #[test]
+    fn predicates_agree_with_events() {
+        for kind in EventKind::ALL {
+            let event = Converter::new().layer(sample(kind));
+            let hat = matches!(event, crate::Event::Hat(..));
+            assert_eq!(kind.is_button(), event.pressed().is_some() || hat, "{kind:?}");
+            assert_eq!(kind.is_axis(), matches!(event, crate::Event::Axis(..)), "{kind:?}");
+            assert_eq!(kind.is_hat_direction(), hat, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn codes_decode_to_their_kind() {
+        for kind in EventKind::ALL {
+            for &(type_, code) in kind.codes() {
+                // A hat axis decodes to one direction at either end.
+                let values: &[i32] = if kind.is_hat_direction() { &[-1, 1] } else { &[1] };
+                let kinds: Vec<_> = values
+                    .iter()
+                    .filter_map(|&value| Event::<i32>::from_input_event(&raw(type_, code, value), &mut 0))
+                    .map(|event| event.kind())
+                    .collect();
+                assert!(kinds.contains(&kind), "{kind:?} {type_} {code:#x}");
+            }
+        }
+        let numbers: Vec<_> = NUMBER_CODES
+            .iter()
+            .map(|&(type_, code)| Event::<i32>::from_input_event(&raw(type_, code, 1), &mut 0))
+            .collect();
+        assert_eq!(numbers, (1..=50).map(|n| Some(Event::Number(n, true))).collect::<Vec<_>>());
+        assert!(EventKind::Repeat.codes().is_empty());
+        assert_eq!(Event::<i32>::from_input_event(&raw(KEY, BTN_SOUTH as u16, 2), &mut 0), Some(Event::Repeat(BTN_SOUTH as u16)));
+    }
+}
@@ -0,0 +1,426 @@
+//! The keys of a layered [`Event`](crate::Event): every button, axis and
+//! hat is one variant of a small `Copy` enum, usable as a map key, and a
+//! hat points in one [`Direction`] at a time.
+
+use crate::kind::EventKind;
+
+/// A button or switch, the key of [`Event::Button`](crate::Event::Button).
+///
+/// A switch position is a button that stays pressed while the switch is in
+/// that position; [`crate::switch`] merges the two ends of a three-way
+/// switch into one position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Button {
+    /// Exit / Main / Home / Mode
+    Exit,
+    /// A / 1 / 4 / Circle, the primary action.
+    ActionA,
+    /// B / 2 / 3 / Cross, the secondary action.
+    ActionB,
+    /// C, the tertiary action.
+    ActionC,
+    /// Y / X / Square, the horizontal action.
+    ActionH,
+    /// X / Y / Triangle, the vertical action.
+    ActionV,
+    /// Z of a 6-button layout.
+    ActionD,
+    /// Back / Select / Minus / Stop
+    MenuL,
+    /// Forward / Start / Plus / Play
+    MenuR,
+    /// Pushing the main (left) stick.
+    Joy,
+    /// Pushing the camera (right) stick.
+    Cam,
+    /// Left shoulder button, the near one if there are two.
+    BumperL,
+    /// Right shoulder button, the near one if there are two.
+    BumperR,
+    /// D-Pad up.  Pads that report their D-Pad as a hat give [`Hat::Pov`]
+    /// instead.
+    Up,
+    /// D-Pad down.
+    Down,
+    /// D-Pad left.
+    Left,
+    /// D-Pad right.
+    Right,
+    /// Pushing the mic hat.
+    MicPush,
+    /// Flightstick trigger on the back of the grip.
+    Trigger,
+    /// Flightstick side button.
+    Bumper,
+    /// Flightstick top middle button.
+    ActionM,
+    /// Flightstick top left button.
+    ActionL,
+    /// Flightstick top right button.
+    ActionR,
+    Pinky,
+    /// Pinky switch in its forward position.
+    PinkyForward,
+    /// Pinky switch in its backward position.
+    PinkyBackward,
+    /// Flaps switch forward (up).  With neither position pressed the flaps
+    /// are at maneuver.
+    FlapsUp,
+    /// Flaps switch backward (down).
+    FlapsDown,
+    /// Boat switch in its forward position.
+    BoatForward,
+    /// Boat switch in its backward position.
+    BoatBackward,
+    /// Autopilot switch forward (path).  With neither position pressed the
+    /// autopilot holds altitude and heading.
+    AutopilotPath,
+    /// Autopilot switch backward (altitude).
+    AutopilotAlt,
+    /// Left engine operate switch backward (motor), normal when released.
+    EngineMotorL,
+    /// Right engine operate switch backward (motor), normal when released.
+    EngineMotorR,
+    /// Left engine fuel flow switch: pressed is normal, released override.
+    EngineFuelFlowL,
+    /// Right engine fuel flow switch: pressed is normal, released override.
+    EngineFuelFlowR,
+    /// Left engine operate switch forward (ignition), normal when released.
+    EngineIgnitionL,
+    /// Right engine operate switch forward (ignition), normal when released.
+    EngineIgnitionR,
+    /// Speedbrake switch in its backward position.
+    SpeedbrakeBackward,
+    /// Speedbrake switch in its forward position.
+    SpeedbrakeForward,
+    /// China hat pushed backward.
+    ChinaBackward,
+    /// China hat pushed forward.
+    ChinaForward,
+    /// APU (auxiliary power unit) switch: pressed is start, released off.
+    Apu,
+    /// Radar altimeter switch: pressed is normal, released disabled.
+    RadarAltimeter,
+    /// Silences the landing gear horn.
+    LandingGearSilence,
+    /// EAC (enhanced attitude control) switch: pressed is arm, released off.
+    Eac,
+    /// Engages or disengages the autopilot.
+    AutopilotToggle,
+    /// Button on the (left) throttle.
+    ThrottleButton,
+    /// Primary mouse button.
+    Mouse,
+    /// Programmable button.  Unlabeled buttons are numbered left to right,
+    /// then top to bottom.
+    Number(i8),
+    /// Back left grip paddle, the upper one if there are two.
+    PaddleLeft,
+    /// Back right grip paddle, the upper one if there are two.
+    PaddleRight,
+    /// Left pinky button, or the lower back right grip paddle.
+    PinkyLeft,
+    /// Right pinky button, or the lower back left grip paddle.
+    PinkyRight,
+    /// Mouse context menu button (right click).
+    Context,
+    /// Mouse DPI button.
+    Dpi,
+    /// Pushing the mouse scroll wheel.
+    Scroll,
+}
+
+/// An analog axis, the key of [`Event::Axis`](crate::Event::Axis).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    /// Left analog trigger, the far shoulder button.
+    TriggerL,
+    /// Right analog trigger, the far shoulder button.
+    TriggerR,
+    /// Main stick horizontal (A / D).
+    JoyX,
+    /// Main stick vertical or depth (W / S).
+    JoyY,
+    /// Main stick twist (yaw).
+    JoyZ,
+    /// Camera (right) stick horizontal, used like mouse X.
+    CamX,
+    /// Camera (right) stick vertical, used like mouse Y.
+    CamY,
+    /// Camera stick twist.
+    CamZ,
+    Slew,
+    /// Single throttle.
+    Throttle,
+    /// Left lever of a split throttle.
+    ThrottleL,
+    /// Right lever of a split throttle.
+    ThrottleR,
+    Volume,
+    /// Steering wheel.
+    Wheel,
+    Rudder,
+    /// Gas pedal.
+    Gas,
+    /// Brake pedal.
+    Brake,
+    /// Relative horizontal mouse movement.
+    MouseX,
+    /// Relative vertical mouse movement.
+    MouseY,
+    /// Horizontal scroll wheel.
+    ScrollX,
+    /// Vertical scroll wheel.
+    ScrollY,
+    /// Horizontal axis under the action buttons.
+    ActionWheelX,
+    /// Vertical axis under the action buttons.
+    ActionWheelY,
+}
+
+/// A four-way hat, the key of [`Event::Hat`](crate::Event::Hat).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hat {
+    /// Point of view hat (`ABS_HAT0X` / `ABS_HAT0Y`), the main hat of a
+    /// flightstick or the D-Pad of most pads.
+    Pov,
+    /// Second hat (`ABS_HAT1X` / `ABS_HAT1Y`).
+    Extra,
+    /// Trim hat (`ABS_HAT2X` / `ABS_HAT2Y`).
+    Trim,
+    /// Mic hat (`ABS_HAT3X` / `ABS_HAT3Y`).
+    Mic,
+}
+
+impl Hat {
+    pub const ALL: [Hat; 4] = [Hat::Pov, Hat::Extra, Hat::Trim, Hat::Mic];
+}
+
+/// Position of a [`Hat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    #[default]
+    Center,
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction {
+    /// The direction of a horizontal and a vertical component, each `-1`,
+    /// `0` or `1`, with up being negative as in evdev.
+    pub fn from_xy(x: i8, y: i8) -> Self {
+        match (x.signum(), y.signum()) {
+            (0, 0) => Direction::Center,
+            (0, -1) => Direction::Up,
+            (1, -1) => Direction::UpRight,
+            (1, 0) => Direction::Right,
+            (1, 1) => Direction::DownRight,
+            (0, 1) => Direction::Down,
+            (-1, 1) => Direction::DownLeft,
+            (-1, 0) => Direction::Left,
+            _ => Direction::UpLeft,
+        }
+    }
+
+    /// Horizontal component, `-1` for left.
+    pub fn x(self) -> i8 {
+        match self {
+            Direction::Left | Direction::UpLeft | Direction::DownLeft => -1,
+            Direction::Right | Direction::UpRight | Direction::DownRight => 1,
+            _ => 0,
+        }
+    }
+
+    /// Vertical component, `-1` for up.
+    pub fn y(self) -> i8 {
+        match self {
+            Direction::Up | Direction::UpLeft | Direction::UpRight => -1,
+            Direction::Down | Direction::DownLeft | Direction::DownRight => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Where each [`Hat`] points.
+///
+/// evdev reports the horizontal and vertical component of a hat as separate
+/// axes, so decoding them into [`Direction`]s needs the other component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HatState {
+    hats: [Direction; 4],
+}
+
+impl HatState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn direction(&self, hat: Hat) -> Direction {
+        self.hats[hat as usize]
+    }
+
+    /// Point a hat in `direction`, returning the `Up`, `Down`, `Left` and
+    /// `Right` components released and pressed on the way, releases first.
+    /// A move from `Up` to `Right` gives `(Up, false)` and `(Right, true)`.
+    pub fn set(&mut self, hat: Hat, direction: Direction) -> Vec<(Direction, bool)> {
+        let old = std::mem::replace(&mut self.hats[hat as usize], direction);
+        let component = |direction: Direction, x: bool| {
+            match (x, if x { direction.x() } else { direction.y() }) {
+                (true, -1) => Some(Direction::Left),
+                (true, 1) => Some(Direction::Right),
+                (false, -1) => Some(Direction::Up),
+                (false, 1) => Some(Direction::Down),
+                _ => None,
+            }
+        };
+        let mut changes = Vec::new();
+        // Releases first, so opposite directions are never held together.
+        for x in [true, false] {
+            if let Some(released) = component(old, x).filter(|d| component(direction, x) != Some(*d)) {
+                changes.push((released, false));
+            }
+        }
+        for x in [true, false] {
+            if let Some(pressed) = component(direction, x).filter(|d| component(old, x) != Some(*d)) {
+                changes.push((pressed, true));
+            }
+        }
+        changes
+    }
+
+    /// Set one component of a hat from the value of its `ABS_HAT*` axis.
+    pub(crate) fn set_axis(&mut self, hat: Hat, vertical: bool, value: i32) -> Direction {
+        let current = self.direction(hat);
+        let value = value.signum() as i8;
+        let direction = if vertical {
+            Direction::from_xy(current.x(), value)
+        } else {
+            Direction::from_xy(value, current.y())
+        };
+        self.hats[hat as usize] = direction;
+        direction
+    }
+}
+
+impl Button {
+    pub fn kind(self) -> EventKind {
+        match self {
+            Button::Exit => EventKind::Exit,
+            Button::ActionA => EventKind::ActionA,
+            Button::ActionB => EventKind::ActionB,
+            Button::ActionC => EventKind::ActionC,
+            Button::ActionH => EventKind::ActionH,
+            Button::ActionV => EventKind::ActionV,
+            Button::ActionD => EventKind::ActionD,
+            Button::MenuL => EventKind::MenuL,
+            Button::MenuR => EventKind::MenuR,
+            Button::Joy => EventKind::Joy,
+            Button::Cam => EventKind::Cam,
+            Button::BumperL => EventKind::BumperL,
+            Button::BumperR => EventKind::BumperR,
+            Button::Up => EventKind::Up,
+            Button::Down => EventKind::Down,
+            Button::Left => EventKind::Left,
+            Button::Right => EventKind::Right,
+            Button::MicPush => EventKind::MicPush,
+            Button::Trigger => EventKind::Trigger,
+            Button::Bumper => EventKind::Bumper,
+            Button::ActionM => EventKind::ActionM,
+            Button::ActionL => EventKind::ActionL,
+            Button::ActionR => EventKind::ActionR,
+            Button::Pinky => EventKind::Pinky,
+            Button::PinkyForward => EventKind::PinkyForward,
+            Button::PinkyBackward => EventKind::PinkyBackward,
+            Button::FlapsUp => EventKind::FlapsUp,
+            Button::FlapsDown => EventKind::FlapsDown,
+            Button::BoatForward => EventKind::BoatForward,
+            Button::BoatBackward => EventKind::BoatBackward,
+            Button::AutopilotPath => EventKind::AutopilotPath,
+            Button::AutopilotAlt => EventKind::AutopilotAlt,
+            Button::EngineMotorL => EventKind::EngineMotorL,
+            Button::EngineMotorR => EventKind::EngineMotorR,
+            Button::EngineFuelFlowL => EventKind::EngineFuelFlowL,
+            Button::EngineFuelFlowR => EventKind::EngineFuelFlowR,
+            Button::EngineIgnitionL => EventKind::EngineIgnitionL,
+            Button::EngineIgnitionR => EventKind::EngineIgnitionR,
+            Button::SpeedbrakeBackward => EventKind::SpeedbrakeBackward,
+            Button::SpeedbrakeForward => EventKind::SpeedbrakeForward,
+            Button::ChinaBackward => EventKind::ChinaBackward,
+            Button::ChinaForward => EventKind::ChinaForward,
+            Button::Apu => EventKind::Apu,
+            Button::RadarAltimeter => EventKind::RadarAltimeter,
+            Button::LandingGearSilence => EventKind::LandingGearSilence,
+            Button::Eac => EventKind::Eac,
+            Button::AutopilotToggle => EventKind::AutopilotToggle,
+            Button::ThrottleButton => EventKind::ThrottleButton,
+            Button::Mouse => EventKind::Mouse,
+            Button::Number(_) => EventKind::Number,
+            Button::PaddleLeft => EventKind::PaddleLeft,
+            Button::PaddleRight => EventKind::PaddleRight,
+            Button::PinkyLeft => EventKind::PinkyLeft,
+            Button::PinkyRight => EventKind::PinkyRight,
+            Button::Context => EventKind::Context,
+            Button::Dpi => EventKind::Dpi,
+            Button::Scroll => EventKind::Scroll,
+        }
+    }
+}
+
+impl Axis {
+    pub const ALL: [Axis; 23] = [
+        Axis::TriggerL, Axis::TriggerR, Axis::JoyX, Axis::JoyY, Axis::JoyZ, Axis::CamX, Axis::CamY,
+        Axis::CamZ, Axis::Slew, Axis::Throttle, Axis::ThrottleL, Axis::ThrottleR, Axis::Volume,
+        Axis::Wheel, Axis::Rudder, Axis::Gas, Axis::Brake, Axis::MouseX, Axis::MouseY,
+        Axis::ScrollX, Axis::ScrollY, Axis::ActionWheelX, Axis::ActionWheelY,
+    ];
+
+    pub fn kind(self) -> EventKind {
+        match self {
+            Axis::TriggerL => EventKind::TriggerL,
+            Axis::TriggerR => EventKind::TriggerR,
+            Axis::JoyX => EventKind::JoyX,
+            Axis::JoyY => EventKind::JoyY,
+            Axis::JoyZ => EventKind::JoyZ,
+            Axis::CamX => EventKind::CamX,
+            Axis::CamY => EventKind::CamY,
+            Axis::CamZ => EventKind::CamZ,
+            Axis::Slew => EventKind::Slew,
+            Axis::Throttle => EventKind::Throttle,
+            Axis::ThrottleL => EventKind::ThrottleL,
+            Axis::ThrottleR => EventKind::ThrottleR,
+            Axis::Volume => EventKind::Volume,
+            Axis::Wheel => EventKind::Wheel,
+            Axis::Rudder => EventKind::Rudder,
+            Axis::Gas => EventKind::Gas,
+            Axis::Brake => EventKind::Brake,
+            Axis::MouseX => EventKind::MouseX,
+            Axis::MouseY => EventKind::MouseY,
+            Axis::ScrollX => EventKind::ScrollX,
+            Axis::ScrollY => EventKind::ScrollY,
+            Axis::ActionWheelX => EventKind::ActionWheelX,
+            Axis::ActionWheelY => EventKind::ActionWheelY,
+        }
+    }
+}
+
+impl From<Button> for EventKind {
+    fn from(button: Button) -> Self {
+        button.kind()
+    }
+}
+
+impl From<Axis> for EventKind {
+    fn from(axis: Axis) -> Self {
+        axis.kind()
+    }
+}
@@ -1,535 +1,459 @@
-#[derive(Debug)]
+/// A decoded input event.
+///
+/// Buttons, axes and hats each have one variant, keyed by the small `Copy`
+/// enums [`Button`], [`Axis`] and [`Hat`].  The flat shape of earlier
+/// versions, one variant per control, is still available in [`flat`].
+///
+/// With the `serde` feature enabled events use serde's externally tagged
+/// representation, keyed by variant name: `"Disconnect"`,
+/// `{"Button":["ActionA",true]}`, `{"Axis":["JoyX",-512]}`,
+/// `{"Hat":["Pov","UpLeft"]}`, `{"Button":[{"Number":3},true]}`.  Variant
+/// names are part of the stable format and are not renamed between
+/// versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event<T = i32> where T: TryFrom<i32> {
-//// Controller unplugged.
+    /// Controller unplugged.
     Disconnect,
-    /// Exit / Main / Home / Mode
-    Exit(bool),
-    /// A / 1 / 4 / Circle.  Action A (Primary action).
-    ActionA(bool),
-    /// B / 2 / 3 / Cross.  Action B (Secondary action).
-    ActionB(bool),
-    /// C.  Action C (Tertiary action).
-    ActionC(bool),
-    /// Y / X / Square.  Action H (Horizontal action).
-    ActionH(bool),
-    /// X / Y / Triangle.  Action V (Vertical action).
-    ActionV(bool),
-    /// Z (in 6-button layout).  Action D.
-    ActionD(bool),
-    /// Left Menu / Back / Select / Minus / Stop
-    MenuL(bool),
-    /// Right Menu / Forward / Start / Plus / Play
-    MenuR(bool),
-    /// Thumb Push Button On Main / Left Joystick
-    Joy(bool),
-    /// Thumb Push Button On Camera / Right Joystick
-    Cam(bool),
-    /// Left shoulder button (near button if no trigger)
-    BumperL(bool),
-    /// Right shoulder button (near button if no trigger)
-    BumperR(bool),
-    /// Left Bumper Trigger (far button if no trigger)
-    TriggerL(T),
-    /// Right Bumper Trigger (far button if no trigger)
-    TriggerR(T),
-    /// D-Pad Up
-    Up(bool),
-    /// D-Pad Down
-    Down(bool),
-    /// D-Pad Left
-    Left(bool),
-    /// D-Pad Right
-    Right(bool),
-    /// POV/Main Hat Left
-    PovUp(bool),
-    /// POV/Main Hat Down
-    PovDown(bool),
-    /// POV/Main Hat Left
-    PovLeft(bool),
-    /// POV/Main Hat Right
-    PovRight(bool),
-    /// Extra Hat Up
-    HatUp(bool),
-    /// Extra Hat Down
-    HatDown(bool),
-    /// Extra Hat Left
-    HatLeft(bool),
-    /// Extra Hat Right
-    HatRight(bool),
-    /// Trim Hat Up
-    TrimUp(bool),
-    /// Trim Hat Down
-    TrimDown(bool),
-    /// Trim Hat Left
-    TrimLeft(bool),
-    /// Trim Hat Right
-    TrimRight(bool),
-    /// Mic Hat Up
-    MicUp(bool),
-    /// Mic Hat Down
-    MicDown(bool),
-    /// Mic Hat Left
-    MicLeft(bool),
-    /// Mic Hat Right
-    MicRight(bool),
-    /// Main stick horizontal axis (A / D)
-    JoyX(T),
-    /// Main stick vertical / depth axis (W / S)
-    JoyY(T),
-    /// Main stick rotation / yaw axis
-    JoyZ(T),
-    /// Secondary stick X axis (Mouse X Position)
-    CamX(T),
-    /// Secondary stick Y axis (Mouse Y Position)
-    CamY(T),
-    /// Secondary stick Z axis
-    CamZ(T),
-    /// Slew Control
-    Slew(T),
-    /// Stationary throttle
-    Throttle(T),
-    /// Left stationary throttle
-    ThrottleL(T),
-    /// Right stationary throttle
-    ThrottleR(T),
-    /// Volume axis
-    Volume(T),
-    /// Steering wheel
-    Wheel(T),
-    /// Ship rudder
-    Rudder(T),
-    /// Gas Pedal
-    Gas(T),
-    /// Brake Pedal
-    Brake(T),
-    /// Mic Hat Push Button
-    MicPush(bool),
-    /// Flightstick trigger button on the back.
-    Trigger(bool),
-    /// Flightstick Side Bumper Button
-    Bumper(bool),
-    /// Flightstick Top Middle Action Button
-    ActionM(bool),
-    /// Flightstick Top Left Action Button
-    ActionL(bool),
-    /// Flightstick Top Right Action Button
-    ActionR(bool),
-    /// Pinky Button
-    Pinky(bool),
-    /// Pinky three-way switch Forward.
-    PinkyForward(bool),
-    /// Pinky three-way switch Backward.
-    PinkyBackward(bool),
-    /// Flaps three-way switch Forward.
-    /// - `true` - Forward (Up)
-    /// - `false` - Neutral (Maneuver)
-    FlapsUp(bool),
-    /// Flaps three-way switch Backward.
-    /// - `true` - Backward (Down)
-    /// - `false` - Neutral (Maneuver)
-    FlapsDown(bool),
-    /// Boat three-way switch Forward.
-    BoatForward(bool),
-    /// Boat three-way switch Backward.
-    BoatBackward(bool),
-    /// Autopilot three-way switch Forward.
-    /// - `true` - Forward (Path)
-    /// - `false` - Neutral (Altitude / Heading)
-    AutopilotPath(bool),
-    /// Autopilot three-way switch Backward.
-    /// - `true` - Backward (Alt)
-    /// - `false` - Neutral (Altitude / Heading)
-    AutopilotAlt(bool),
-    /// Left Engine Operate three-way switch Backward.
-    /// - `true` - Backward (Motor)
-    /// - `false` - Neutral (Normal)
-    EngineMotorL(bool),
-    /// Right Engine Operate three-way switch Backward.
-    /// - `true` - Backward (Motor)
-    /// - `false` - Neutral (Normal)
-    EngineMotorR(bool),
-    /// Engine Fuel Flow Left two-way switch
-    /// - `true` - Normal
-    /// - `false` - Override
-    EngineFuelFlowL(bool),
-    /// Engine Fuel Flow Right two-way switch
-    /// - `true` - Normal
-    /// - `false` - Override
-    EngineFuelFlowR(bool),
-    /// Left Engine Operate three-way switch Forward.
-    /// - `true` - Forward (Ignition)
-    /// - `false` - Neutral (Normal)
-    EngineIgnitionL(bool),
-    /// Right Engine Operate three-way switch Forward.
-    /// - `true` - Forward (Ignition)
-    /// - `false` - Neutral (Normal)
-    EngineIgnitionR(bool),
-    /// Speedbrake three-way switch Backward.
-    SpeedbrakeBackward(bool),
-    /// Speedbrake three-way switch Forward.
-    SpeedbrakeForward(bool),
-    /// China hat three-way switch Backward.
-    ChinaBackward(bool),
-    /// China hat three-way switch Forward.
-    ChinaForward(bool),
-    /// APU (Auxiliary Power Unit) two-way switch
-    /// - `true` - Start
-    /// - `false` - Off
-    Apu(bool),
-    /// Radar Altimeter two-way switch (Altitude measurements)
-    /// - `true` - Normal
-    /// - `false` - Disabled
-    RadarAltimeter(bool),
-    /// Landing Gear Horn Silence Button
-    LandingGearSilence(bool),
-    /// EAC (Enhanced Attitude Control - Autopilot) two-way switch
-    /// - `true` - Arm
-    /// - `false` - Off
-    Eac(bool),
-    /// Autopilot Toggle Button
-    AutopilotToggle(bool),
-    /// Throttle button (Left)
-    ThrottleButton(bool),
-    /// Mouse delta position horizontal
-    MouseX(T),
-    /// Mouse delta position vertical
-    MouseY(T),
-    /// Mouse primary button
-    Mouse(bool),
-    /// Numbered or unlabeled programmable action buttons (If unlabelled,
-    /// prefer numbering from left to right, upper to lower)
-    Number(i8, bool),
-    /// Back left grip button (upper if there are two)
-    PaddleLeft(bool),
-    /// Back right grip button (upper if there are two)
-    PaddleRight(bool),
-    /// Left Pinky Button / Back lower right grip button
-    PinkyLeft(bool),
-    /// Right Pinky Button / Back lower left grip button
-    PinkyRight(bool),
-    /// Context Menu Button on a mouse (Right Click)
-    Context(bool),
-    /// DPI Button on a mouse
-    Dpi(bool),
-    /// Scroll Wheel X on a mouse
-    ScrollX(T),
-    /// Scroll Wheel Y on a mouse
-    ScrollY(T),
-    /// Scroll Button on a mouse
-    Scroll(bool),
-    /// Horizontal axis under the action buttons
-    ActionWheelX(T),
-    /// Vertical axis under the action buttons
-    ActionWheelY(T),
+    /// A button or switch, and whether it is pressed.
+    Button(Button, bool),
+    /// An analog axis and its position.
+    Axis(Axis, T),
+    /// A hat and where it points.
+    Hat(Hat, Direction),
+    /// Kernel autorepeat of a held key, carrying the key code.  The typed
+    /// button can be recovered with [`Event::from_key`].
+    Repeat(u16),
+    /// Delay before a held key starts repeating (milliseconds)
+    RepeatDelay(T),
+    /// Period between repeats of a held key (milliseconds)
+    RepeatPeriod(T),
+    /// Power management event (`EV_PWR`), carrying the raw code
+    Power(u16, T),
+    /// Keyboard click sound
+    Click(bool),
+    /// Bell sound
+    Bell(bool),
+    /// PC speaker tone (Hz, `0` for off)
+    Tone(T),
 }
 
+pub mod kind;
+pub use kind::EventKind;
+
+pub mod layered;
+pub use layered::{Axis, Button, Direction, Hat, HatState};
+
+pub mod flat;
+
+mod device;
+pub use device::{AbsInfo, Device, InputId, InputProp, InputProps, RepeatSettings};
+
+mod grab;
+pub use grab::Grab;
+
+pub mod identity;
+pub use identity::{Identity, StableId};
+
+pub mod monitor;
+pub use monitor::{DeviceClass, DeviceInfo, DeviceMonitor, MonitorEvent};
+
+pub mod set;
+pub use set::{DeviceId, DeviceSet};
+
+pub mod joydev;
+pub use joydev::Joydev;
+
+pub mod evemu;
+
+mod uinput;
+pub use uinput::VirtualDevice;
+
+pub mod replay;
+pub use replay::Replayer;
+
+pub mod pipeline;
+pub use pipeline::{EventPipeline, Stage};
+
+pub mod axis;
+
+pub mod digital;
+
+pub mod chord;
+pub use chord::{ChordDetector, ChordEvent};
+
+pub mod gesture;
+pub use gesture::{Gesture, GestureRecognizer};
+
+pub mod switch;
+pub use switch::{Switch, SwitchTracker, ThreeWay};
+
+pub mod time;
+pub use time::{Clock, Timestamp};
+
+pub mod repeat;
+pub use repeat::{RepeatConfig, RepeatEvent, Repeater};
+
+pub mod calibration;
+pub use calibration::{CalibrationProfile, Calibrator};
+
+pub mod tablet;
+pub use tablet::{Tablet, TabletConfig, TabletDecoder, TabletEvent, Tool};
+
+pub mod motion;
+pub use motion::{Motion, MotionDecoder, MotionEvent};
+
+pub mod decode;
+pub use decode::{DecodedEvent, Decoder, Interpretation};
+
 use input_linux_sys::*;
 
+// Codes 0x0B..=0x0F are unassigned by the kernel and used by some HOTAS
+// drivers for their extra axes.
+pub(crate) const ABS_UNKNOWN0: i32 = 0x0B;
+pub(crate) const ABS_UNKNOWN1: i32 = 0x0C;
+pub(crate) const ABS_UNKNOWN2: i32 = 0x0D;
+pub(crate) const ABS_UNKNOWN3: i32 = 0x0E;
+pub(crate) const ABS_UNKNOWN4: i32 = 0x0F;
+
 impl <T> Event<T> where T: TryFrom<i32> {
-    pub fn from_input_event(input_event: &input_event, state: &mut u8) -> Option<Self> {
+    /// Decode a raw event, tracking the hats in `hats`.  Events there is no
+    /// variant for, e.g. keyboard keys, LEDs or switches, give `None`.
+    pub fn from_input_event(input_event: &input_event, hats: &mut HatState) -> Option<Self> {
         match input_event.type_ as _ {
-            EV_ABS => Self::from_abs_event(input_event, state),
-            EV_CNT => todo!(),
-            EV_FF => None, //ignore force feedback events
-            EV_FF_STATUS => todo!(),
+            EV_ABS => Self::from_abs_event(input_event, hats),
             EV_KEY => Self::from_key_event(input_event),
-            EV_LED => todo!(),
-            EV_MAX => todo!(),
-            EV_MSC => { //ignore misc/scan events
-                if input_event.code != 4 {
-                    panic!()
-                }
-                None
-            }
-            EV_PWR => todo!(),
+            EV_PWR => Some(Event::Power(input_event.code, input_event.value.try_into().ok()?)),
             EV_REL => Event::from_rel_event(input_event),
-            EV_REP => todo!(),
-            EV_SND => todo!(),
-            EV_SW => todo!(),
-            EV_SYN => None, //ignore syn input events
-            EV_UINPUT => todo!(),
-            _unknown => panic!("unknown input type"),
+            EV_REP => Self::from_rep_event(input_event),
+            EV_SND => Self::from_snd_event(input_event),
+            // Force feedback, misc/scan, LED, switch and syn events
+            _ => None,
         }
     }
 
     fn from_key_event(input_event: &input_event) -> Option<Self> {
-        let key = input_event.code;
-        let val = input_event.value.try_into().ok()?;
-        let pushed = input_event.value != 0;
-
-        Some(match key {
-            0x08B /* KEY_MENU */ => Event::Context(pushed),
-
-            0x09E /* KEY_BACK */ => Event::PaddleLeft(pushed),
-            0x09F /* KEY_FORWARD */ => Event::PaddleRight(pushed),
-
-            0x120 /* BTN_TRIGGER */ => Event::Trigger(pushed),
-            0x121 /* BTN_THUMB */ => Event::ActionM(pushed),
-            0x122 /* BTN_THUMB2 */ => Event::Bumper(pushed),
-            0x123 /* BTN_TOP */ => Event::ActionR(pushed),
-            0x124 /* BTN_TOP2 */ => Event::ActionL(pushed),
-            0x125 /* BTN_PINKIE */ => Event::Pinky(pushed),
-            0x126 /* BTN_BASE1 */ => Event::Number(1, pushed),
-            0x127 /* BTN_BASE2 */ => Event::Number(2, pushed),
-            0x128 /* BTN_BASE3 */ => Event::Number(3, pushed),
-            0x129 /* BTN_BASE4 */ => Event::Number(4, pushed),
-            0x12A /* BTN_BASE5 */ => Event::Number(5, pushed),
-            0x12B /* BTN_BASE6 */ => Event::Number(6, pushed),
-            0x12C /* BTN_BASE7 */ => Event::Number(7, pushed),
-            0x12D /* BTN_BASE8 */ => Event::Number(8, pushed),
-            0x12E /* BTN_BASE9 */ => Event::Number(9, pushed),
-            0x12F /* BTN_BASE10 */ => Event::Number(10, pushed),
-
-            0x130 /* BTN_A / BTN_SOUTH */ => Event::ActionA(pushed),
-            0x131 /* BTN_B / BTN_EAST */ => Event::ActionB(pushed),
-            0x132 /* BTN_C */ => Event::ActionC(pushed),
-            0x133 /* BTN_X / BTN_NORTH */ => Event::ActionV(pushed),
-            0x134 /* BTN_Y / BTN_WEST */ => Event::ActionH(pushed),
-            0x135 /* BTN_Z */ => Event::ActionD(pushed),
-            0x136 /* BTN_TL */ => Event::BumperL(pushed),
-            0x137 /* BTN_TR */ => Event::BumperR(pushed),
-            0x138 /* BTN_TL2 */ => Event::TriggerL(val),
-            0x139 /* BTN_TR2 */ => Event::TriggerR(val),
-            0x13A /* BTN_SELECT */ => Event::MenuL(pushed),
-            0x13B /* BTN_START */ => Event::MenuR(pushed),
-            0x13C /* BTN_MODE */ => Event::Exit(pushed),
-            0x13D /* BTN_THUMBL */ => Event::Joy(pushed),
-            0x13E /* BTN_THUMBR */ => Event::Cam(pushed),
-            0x13F /* BTN_PINKYR */ => Event::PinkyRight(pushed),
-            0x140 /* BTN_PINKYL */ => Event::PinkyLeft(pushed),
-
-            0x220 /* BTN_DPAD_UP */ => Event::Up(pushed),
-            0x221 /* BTN_DPAD_DOWN */ => Event::Down(pushed),
-            0x222 /* BTN_DPAD_LEFT */ => Event::Left(pushed),
-            0x223 /* BTN_DPAD_RIGHT */ => Event::Right(pushed),
-
-            0x2C0 /* BTN_TRIGGER_HAPPY1 */ => Event::Number(11, pushed),
-            0x2C1 /* BTN_TRIGGER_HAPPY2 */ => Event::Number(12, pushed),
-            0x2C2 /* BTN_TRIGGER_HAPPY3 */ => Event::Number(13, pushed),
-            0x2C3 /* BTN_TRIGGER_HAPPY4 */ => Event::Number(14, pushed),
-            0x2C4 /* BTN_TRIGGER_HAPPY5 */ => Event::Number(15, pushed),
-            0x2C5 /* BTN_TRIGGER_HAPPY6 */ => Event::Number(16, pushed),
-            0x2C6 /* BTN_TRIGGER_HAPPY7 */ => Event::Number(17, pushed),
-            0x2C7 /* BTN_TRIGGER_HAPPY8 */ => Event::Number(18, pushed),
-            0x2C8 /* BTN_TRIGGER_HAPPY9 */ => Event::Number(19, pushed),
-            0x2C9 /* BTN_TRIGGER_HAPPY10 */ => Event::Number(20, pushed),
-            0x2CA /* BTN_TRIGGER_HAPPY11 */ => Event::Number(21, pushed),
-            0x2CB /* BTN_TRIGGER_HAPPY12 */ => Event::Number(22, pushed),
-            0x2CC /* BTN_TRIGGER_HAPPY13 */ => Event::Number(23, pushed),
-            0x2CD /* BTN_TRIGGER_HAPPY14 */ => Event::Number(24, pushed),
-            0x2CE /* BTN_TRIGGER_HAPPY15 */ => Event::Number(25, pushed),
-            0x2CF /* BTN_TRIGGER_HAPPY16 */ => Event::Number(26, pushed),
-            0x2D0 /* BTN_TRIGGER_HAPPY17 */ => Event::Number(27, pushed),
-            0x2D1 /* BTN_TRIGGER_HAPPY18 */ => Event::Number(28, pushed),
-            0x2D2 /* BTN_TRIGGER_HAPPY19 */ => Event::Number(29, pushed),
-            0x2D3 /* BTN_TRIGGER_HAPPY20 */ => Event::Number(30, pushed),
-            0x2D4 /* BTN_TRIGGER_HAPPY21 */ => Event::Number(31, pushed),
-            0x2D5 /* BTN_TRIGGER_HAPPY22 */ => Event::Number(32, pushed),
-            0x2D6 /* BTN_TRIGGER_HAPPY23 */ => Event::Number(33, pushed),
-            0x2D7 /* BTN_TRIGGER_HAPPY24 */ => Event::Number(34, pushed),
-            0x2D8 /* BTN_TRIGGER_HAPPY25 */ => Event::Number(35, pushed),
-            0x2D9 /* BTN_TRIGGER_HAPPY26 */ => Event::Number(36, pushed),
-            0x2DA /* BTN_TRIGGER_HAPPY27 */ => Event::Number(37, pushed),
-            0x2DB /* BTN_TRIGGER_HAPPY28 */ => Event::Number(38, pushed),
-            0x2DC /* BTN_TRIGGER_HAPPY29 */ => Event::Number(39, pushed),
-            0x2DD /* BTN_TRIGGER_HAPPY30 */ => Event::Number(40, pushed),
-            0x2DE /* BTN_TRIGGER_HAPPY31 */ => Event::Number(41, pushed),
-            0x2DF /* BTN_TRIGGER_HAPPY32 */ => Event::Number(42, pushed),
-            0x2E0 /* BTN_TRIGGER_HAPPY33 */ => Event::Number(43, pushed),
-            0x2E1 /* BTN_TRIGGER_HAPPY34 */ => Event::Number(44, pushed),
-            0x2E2 /* BTN_TRIGGER_HAPPY35 */ => Event::Number(45, pushed),
-            0x2E3 /* BTN_TRIGGER_HAPPY36 */ => Event::Number(46, pushed),
-            0x2E4 /* BTN_TRIGGER_HAPPY37 */ => Event::Number(47, pushed),
-            0x2E5 /* BTN_TRIGGER_HAPPY38 */ => Event::Number(48, pushed),
-            0x2E6 /* BTN_TRIGGER_HAPPY39 */ => Event::Number(49, pushed),
-            0x2E7 /* BTN_TRIGGER_HAPPY40 */ => Event::Number(50, pushed),
-            other => unimplemented!("unknown button: {other}"),
-        })
+        if input_event.value == 2 {
+            return Some(Event::Repeat(input_event.code));
+        }
+        Self::from_key(input_event.code, input_event.value)
     }
 
-    fn from_rel_event(input_event: &input_event) -> Option<Self> {
-        let axis = input_event.code;
+    /// Decode an `EV_KEY` code with the given value (0 = released,
+    /// 1 = pressed).
+    pub fn from_key(key: u16, value: i32) -> Option<Self> {
+        let val = value.try_into().ok()?;
+        let pushed = value != 0;
+
+        let button = match key {
+            0x08B /* KEY_MENU */ => Button::Context,
+
+            0x09E /* KEY_BACK */ => Button::PaddleLeft,
+            0x09F /* KEY_FORWARD */ => Button::PaddleRight,
+
+            0x120 /* BTN_TRIGGER */ => Button::Trigger,
+            0x121 /* BTN_THUMB */ => Button::ActionM,
+            0x122 /* BTN_THUMB2 */ => Button::Bumper,
+            0x123 /* BTN_TOP */ => Button::ActionR,
+            0x124 /* BTN_TOP2 */ => Button::ActionL,
+            0x125 /* BTN_PINKIE */ => Button::Pinky,
+            0x126 /* BTN_BASE1 */ => Button::Number(1),
+            0x127 /* BTN_BASE2 */ => Button::Number(2),
+            0x128 /* BTN_BASE3 */ => Button::Number(3),
+            0x129 /* BTN_BASE4 */ => Button::Number(4),
+            0x12A /* BTN_BASE5 */ => Button::Number(5),
+            0x12B /* BTN_BASE6 */ => Button::Number(6),
+            0x12C /* BTN_BASE7 */ => Button::Number(7),
+            0x12D /* BTN_BASE8 */ => Button::Number(8),
+            0x12E /* BTN_BASE9 */ => Button::Number(9),
+            0x12F /* BTN_BASE10 */ => Button::Number(10),
+
+            0x130 /* BTN_A / BTN_SOUTH */ => Button::ActionA,
+            0x131 /* BTN_B / BTN_EAST */ => Button::ActionB,
+            0x132 /* BTN_C */ => Button::ActionC,
+            0x133 /* BTN_X / BTN_NORTH */ => Button::ActionV,
+            0x134 /* BTN_Y / BTN_WEST */ => Button::ActionH,
+            0x135 /* BTN_Z */ => Button::ActionD,
+            0x136 /* BTN_TL */ => Button::BumperL,
+            0x137 /* BTN_TR */ => Button::BumperR,
+            0x138 /* BTN_TL2 */ => return Some(Event::Axis(Axis::TriggerL, val)),
+            0x139 /* BTN_TR2 */ => return Some(Event::Axis(Axis::TriggerR, val)),
+            0x13A /* BTN_SELECT */ => Button::MenuL,
+            0x13B /* BTN_START */ => Button::MenuR,
+            0x13C /* BTN_MODE */ => Button::Exit,
+            0x13D /* BTN_THUMBL */ => Button::Joy,
+            0x13E /* BTN_THUMBR */ => Button::Cam,
+            0x13F /* BTN_PINKYR */ => Button::PinkyRight,
+            0x140 /* BTN_PINKYL */ => Button::PinkyLeft,
+
+            0x220 /* BTN_DPAD_UP */ => Button::Up,
+            0x221 /* BTN_DPAD_DOWN */ => Button::Down,
+            0x222 /* BTN_DPAD_LEFT */ => Button::Left,
+            0x223 /* BTN_DPAD_RIGHT */ => Button::Right,
+
+            0x2C0 /* BTN_TRIGGER_HAPPY1 */ => Button::Number(11),
+            0x2C1 /* BTN_TRIGGER_HAPPY2 */ => Button::Number(12),
+            0x2C2 /* BTN_TRIGGER_HAPPY3 */ => Button::Number(13),
+            0x2C3 /* BTN_TRIGGER_HAPPY4 */ => Button::Number(14),
+            0x2C4 /* BTN_TRIGGER_HAPPY5 */ => Button::Number(15),
+            0x2C5 /* BTN_TRIGGER_HAPPY6 */ => Button::Number(16),
+            0x2C6 /* BTN_TRIGGER_HAPPY7 */ => Button::Number(17),
+            0x2C7 /* BTN_TRIGGER_HAPPY8 */ => Button::Number(18),
+            0x2C8 /* BTN_TRIGGER_HAPPY9 */ => Button::Number(19),
+            0x2C9 /* BTN_TRIGGER_HAPPY10 */ => Button::Number(20),
+            0x2CA /* BTN_TRIGGER_HAPPY11 */ => Button::Number(21),
+            0x2CB /* BTN_TRIGGER_HAPPY12 */ => Button::Number(22),
+            0x2CC /* BTN_TRIGGER_HAPPY13 */ => Button::Number(23),
+            0x2CD /* BTN_TRIGGER_HAPPY14 */ => Button::Number(24),
+            0x2CE /* BTN_TRIGGER_HAPPY15 */ => Button::Number(25),
+            0x2CF /* BTN_TRIGGER_HAPPY16 */ => Button::Number(26),
+            0x2D0 /* BTN_TRIGGER_HAPPY17 */ => Button::Number(27),
+            0x2D1 /* BTN_TRIGGER_HAPPY18 */ => Button::Number(28),
+            0x2D2 /* BTN_TRIGGER_HAPPY19 */ => Button::Number(29),
+            0x2D3 /* BTN_TRIGGER_HAPPY20 */ => Button::Number(30),
+            0x2D4 /* BTN_TRIGGER_HAPPY21 */ => Button::Number(31),
+            0x2D5 /* BTN_TRIGGER_HAPPY22 */ => Button::Number(32),
+            0x2D6 /* BTN_TRIGGER_HAPPY23 */ => Button::Number(33),
+            0x2D7 /* BTN_TRIGGER_HAPPY24 */ => Button::Number(34),
+            0x2D8 /* BTN_TRIGGER_HAPPY25 */ => Button::Number(35),
+            0x2D9 /* BTN_TRIGGER_HAPPY26 */ => Button::Number(36),
+            0x2DA /* BTN_TRIGGER_HAPPY27 */ => Button::Number(37),
+            0x2DB /* BTN_TRIGGER_HAPPY28 */ => Button::Number(38),
+            0x2DC /* BTN_TRIGGER_HAPPY29 */ => Button::Number(39),
+            0x2DD /* BTN_TRIGGER_HAPPY30 */ => Button::Number(40),
+            0x2DE /* BTN_TRIGGER_HAPPY31 */ => Button::Number(41),
+            0x2DF /* BTN_TRIGGER_HAPPY32 */ => Button::Number(42),
+            0x2E0 /* BTN_TRIGGER_HAPPY33 */ => Button::Number(43),
+            0x2E1 /* BTN_TRIGGER_HAPPY34 */ => Button::Number(44),
+            0x2E2 /* BTN_TRIGGER_HAPPY35 */ => Button::Number(45),
+            0x2E3 /* BTN_TRIGGER_HAPPY36 */ => Button::Number(46),
+            0x2E4 /* BTN_TRIGGER_HAPPY37 */ => Button::Number(47),
+            0x2E5 /* BTN_TRIGGER_HAPPY38 */ => Button::Number(48),
+            0x2E6 /* BTN_TRIGGER_HAPPY39 */ => Button::Number(49),
+            0x2E7 /* BTN_TRIGGER_HAPPY40 */ => Button::Number(50),
+            _ => return None,
+        };
+        Some(Event::Button(button, pushed))
+    }
+
+    fn from_rep_event(input_event: &input_event) -> Option<Self> {
         let value = input_event.value.try_into().ok()?;
 
-        Some(match axis as _ {
-            REL_X => Event::MouseX(value),
-            REL_Y => Event::MouseY(value),
-            REL_Z => todo!(),
-            REL_RX => todo!(),
-            REL_RY => todo!(),
-            REL_RZ => todo!(),
-            REL_HWHEEL => todo!(),
-            REL_DIAL => todo!(),
-            REL_WHEEL => todo!(),
-            REL_MISC => todo!(),
-            unknown => unimplemented!("unknown rel: {unknown}"),
+        Some(match input_event.code as _ {
+            REP_DELAY => Event::RepeatDelay(value),
+            REP_PERIOD => Event::RepeatPeriod(value),
+            _ => return None,
         })
     }
 
-    fn from_abs_event(input_event: &input_event, state: &mut u8) -> Option<Self> {
-        let axis = input_event.code;
-        let raw_val = input_event.value;
-        let value = raw_val.try_into().ok()?;
-
-        const POV_HOR_OFS: u8 = 0;
-        const POV_VER_OFS: u8 = 1;
-        const HAT_HOR_OFS: u8 = 2;
-        const HAT_VER_OFS: u8 = 3;
-        const TRIM_HOR_OFS: u8 = 4;
-        const TRIM_VER_OFS: u8 = 5;
-        const MIC_HOR_OFS: u8 = 6;
-        const MIC_VER_OFS: u8 = 7;
-
-        Some(match axis as _ {
-            0x00..=0x0F => match axis as _ {
-                ABS_X => Event::JoyX(value),
-                ABS_Y => Event::JoyY(value),
-                ABS_Z => Event::JoyZ(value),
-                ABS_RX => Event::CamX(value),
-                ABS_RY => Event::CamY(value),
-                ABS_RZ => Event::CamZ(value),
-                ABS_THROTTLE => Event::Throttle(value),
-                ABS_RUDDER => Event::Rudder(value),
-                ABS_WHEEL => Event::Wheel(value),
-                ABS_GAS => Event::Gas(value),
-                ABS_BRAKE => Event::Brake(value),
-                ABS_UNKNOWN0 => Event::Slew(value),
-                ABS_UNKNOWN1 => Event::ThrottleL(value),
-                ABS_UNKNOWN2 => Event::ThrottleR(value),
-                ABS_UNKNOWN3 => Event::ScrollX(value),
-                ABS_UNKNOWN4 => Event::ScrollY(value),
-                _ => unreachable!()
-            }
-            0x10..=0x17 => {
-                use std::cmp::Ordering;
-                match (axis as _, raw_val.cmp(&0)) {
-                    (ABS_HAT0X, Ordering::Greater) => {
-                        apply_bitmask_greater::<POV_HOR_OFS>(state);
-                        Event::PovRight(true)
-                    }
-                    (ABS_HAT0X, Ordering::Less) => Event::PovLeft(true),
-                    (ABS_HAT0X, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<POV_HOR_OFS>(state);
-                        if out {
-                            Event::PovRight(false)
-                        }else {
-                            Event::PovLeft(false)
-                        }
-                    }
-
-                    (ABS_HAT0Y, Ordering::Greater) => {
-                        apply_bitmask_greater::<POV_VER_OFS>(state);
-                        Event::PovDown(true)
-                    }
-                    (ABS_HAT0Y, Ordering::Less) => Event::PovUp(true),
-                    (ABS_HAT0Y, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<POV_VER_OFS>(state);
-                        if out {
-                            Event::PovDown(false)
-                        }else {
-                            Event::PovUp(false)
-                        }
-                    }
-
-                    (ABS_HAT1X, Ordering::Greater) => {
-                        apply_bitmask_greater::<HAT_HOR_OFS>(state);
-                        Event::HatRight(true)
-                    }
-                    (ABS_HAT1X, Ordering::Less) => Event::HatLeft(true),
-                    (ABS_HAT1X, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<HAT_HOR_OFS>(state);
-                        if out {
-                            Event::HatRight(false)
-                        }else {
-                            Event::HatLeft(false)
-                        }
-                    }
-
-                    (ABS_HAT1Y, Ordering::Greater) => {
-                        apply_bitmask_greater::<HAT_VER_OFS>(state);
-                        Event::HatDown(true)
-                    }
-                    (ABS_HAT1Y, Ordering::Less) => Event::HatUp(true),
-                    (ABS_HAT1Y, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<HAT_VER_OFS>(state);
-                        if out {
-                            Event::HatDown(false)
-                        }else {
-                            Event::HatUp(false)
-                        }
-                    }
-
-                    (ABS_HAT2X, Ordering::Greater) => {
-                        apply_bitmask_greater::<TRIM_HOR_OFS>(state);
-                        Event::TrimRight(true)
-                    }
-                    (ABS_HAT2X, Ordering::Less) => Event::TrimLeft(true),
-                    (ABS_HAT2X, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<TRIM_HOR_OFS>(state);
-                        if out {
-                            Event::TrimRight(false)
-                        }else {
-                            Event::TrimLeft(false)
-                        }
-                    }
-
-                    (ABS_HAT2Y, Ordering::Greater) => {
-                        apply_bitmask_greater::<TRIM_VER_OFS>(state);
-                        Event::TrimDown(true)
-                    }
-                    (ABS_HAT2Y, Ordering::Less) => Event::TrimUp(true),
-                    (ABS_HAT2Y, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<TRIM_VER_OFS>(state);
-                        if out {
-                            Event::TrimDown(false)
-                        }else {
-                            Event::TrimUp(false)
-                        }
-                    }
-
-                    (ABS_HAT3X, Ordering::Greater) => {
-                        apply_bitmask_greater::<MIC_HOR_OFS>(state);
-                        Event::MicRight(true)
-                    }
-                    (ABS_HAT3X, Ordering::Less) => Event::MicLeft(true),
-                    (ABS_HAT3X, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<MIC_HOR_OFS>(state);
-                        if out {
-                            Event::MicRight(false)
-                        }else {
-                            Event::MicLeft(false)
-                        }
-                    }
-
-                    (ABS_HAT3Y, Ordering::Greater) => {
-                        apply_bitmask_greater::<MIC_VER_OFS>(state);
-                        Event::MicDown(true)
-                    }
-                    (ABS_HAT3Y, Ordering::Less) => Event::MicUp(true),
-                    (ABS_HAT3Y, Ordering::Equal) => {
-                        let out = apply_bitmask_eq::<MIC_VER_OFS>(state);
-                        if out {
-                            Event::MicDown(false)
-                        }else {
-                            Event::MicUp(false)
-                        }
-                    }
-                    _ => unreachable!(),
-                }
-            }
-            unknown => unimplemented!("unknown code: {unknown}"),
+    fn from_snd_event(input_event: &input_event) -> Option<Self> {
+        let on = input_event.value != 0;
+
+        Some(match input_event.code as _ {
+            SND_CLICK => Event::Click(on),
+            SND_BELL => Event::Bell(on),
+            SND_TONE => Event::Tone(input_event.value.try_into().ok()?),
+            _ => return None,
         })
     }
+
+    fn from_rel_event(input_event: &input_event) -> Option<Self> {
+        let value = input_event.value.try_into().ok()?;
+
+        let axis = match input_event.code as _ {
+            REL_X => Axis::MouseX,
+            REL_Y => Axis::MouseY,
+            _ => return None,
+        };
+        Some(Event::Axis(axis, value))
+    }
+
+    fn from_abs_event(input_event: &input_event, hats: &mut HatState) -> Option<Self> {
+        let code = input_event.code as i32;
+        if (ABS_HAT0X..=ABS_HAT3Y).contains(&code) {
+            let index = (code - ABS_HAT0X) as usize;
+            let hat = Hat::ALL[index / 2];
+            return Some(Event::Hat(hat, hats.set_axis(hat, index % 2 == 1, input_event.value)));
+        }
+        let value = input_event.value.try_into().ok()?;
+
+        let axis = match code {
+            ABS_X => Axis::JoyX,
+            ABS_Y => Axis::JoyY,
+            ABS_Z => Axis::JoyZ,
+            ABS_RX => Axis::CamX,
+            ABS_RY => Axis::CamY,
+            ABS_RZ => Axis::CamZ,
+            ABS_THROTTLE => Axis::Throttle,
+            ABS_RUDDER => Axis::Rudder,
+            ABS_WHEEL => Axis::Wheel,
+            ABS_GAS => Axis::Gas,
+            ABS_BRAKE => Axis::Brake,
+            ABS_UNKNOWN0 => Axis::Slew,
+            ABS_UNKNOWN1 => Axis::ThrottleL,
+            ABS_UNKNOWN2 => Axis::ThrottleR,
+            ABS_UNKNOWN3 => Axis::ScrollX,
+            ABS_UNKNOWN4 => Axis::ScrollY,
+            _ => return None,
+        };
+        Some(Event::Axis(axis, value))
+    }
 }
 
-fn apply_bitmask_greater<const BIT_OFFSET: u8>(state: &mut u8) {
-    let mask = 1 << BIT_OFFSET;
-    *state |= mask;
+impl<T> Event<T> where T: TryFrom<i32> {
+    /// Pressed state of a button or switch event.  Sound events carry a
+    /// `bool` too but aren't buttons, and return `None`.
+    pub fn pressed(&self) -> Option<bool> {
+        match self {
+            Event::Button(_, pushed) => Some(*pushed),
+            _ => None,
+        }
+    }
+
+    /// The button of a button or switch event.
+    pub fn button(&self) -> Option<Button> {
+        match self {
+            Event::Button(button, _) => Some(*button),
+            _ => None,
+        }
+    }
+
+    /// The `T` payload: the position of an axis, or the value of a
+    /// setting like [`Event::RepeatDelay`].
+    pub fn value(&self) -> Option<&T> {
+        match self {
+            Event::Axis(_, value) | Event::RepeatDelay(value) | Event::RepeatPeriod(value)
+            | Event::Power(_, value) | Event::Tone(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Convert the `T` payload, keeping everything else, e.g. to turn raw
+    /// `Event<i32>`s into normalized `Event<f64>`s.
+    pub fn map_value<U>(self, f: impl FnOnce(T) -> U) -> Event<U> where U: TryFrom<i32> {
+        match self {
+            Event::Disconnect => Event::Disconnect,
+            Event::Button(button, pushed) => Event::Button(button, pushed),
+            Event::Axis(axis, value) => Event::Axis(axis, f(value)),
+            Event::Hat(hat, direction) => Event::Hat(hat, direction),
+            Event::Repeat(code) => Event::Repeat(code),
+            Event::RepeatDelay(value) => Event::RepeatDelay(f(value)),
+            Event::RepeatPeriod(value) => Event::RepeatPeriod(f(value)),
+            Event::Power(code, value) => Event::Power(code, f(value)),
+            Event::Click(on) => Event::Click(on),
+            Event::Bell(on) => Event::Bell(on),
+            Event::Tone(value) => Event::Tone(f(value)),
+        }
+    }
+
+    /// The same event with a different `T` payload.  Events without one are
+    /// returned unchanged.
+    pub fn with_value(self, value: T) -> Self {
+        self.map_value(|_| value)
+    }
 }
 
-fn apply_bitmask_eq<const BIT_OFFSET: u8>(state: &mut u8) -> bool {
-    let mask = 1 << BIT_OFFSET;
-    let out = (*state & mask) > 0;
-    *state &= !mask;
-    out
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abs(code: u16, value: i32) -> Option<Event> {
+        let raw = input_event { time: timeval { tv_sec: 0, tv_usec: 0 }, type_: EV_ABS as u16, code, value };
+        Event::from_input_event(&raw, &mut HatState::new())
+    }
+
+    #[test]
+    fn unassigned_abs_codes() {
+        assert_eq!(abs(0x0B, 7), Some(Event::Axis(Axis::Slew, 7)));
+        assert_eq!(abs(0x0C, 7), Some(Event::Axis(Axis::ThrottleL, 7)));
+        assert_eq!(abs(0x0D, 7), Some(Event::Axis(Axis::ThrottleR, 7)));
+        assert_eq!(abs(0x0E, 7), Some(Event::Axis(Axis::ScrollX, 7)));
+        assert_eq!(abs(0x0F, 7), Some(Event::Axis(Axis::ScrollY, 7)));
+    }
+
+    fn valued() -> impl Iterator<Item = Event> {
+        Axis::ALL
+            .into_iter()
+            .map(|axis| Event::Axis(axis, -7))
+            .chain([Event::RepeatDelay(250), Event::RepeatPeriod(33), Event::Power(0x74, 1), Event::Tone(440)])
+    }
+
+    #[test]
+    fn with_own_value_is_identity() {
+        for event in valued() {
+            assert_eq!(event.with_value(*event.value().unwrap()), event);
+        }
+        let event = Event::Button(Button::ActionA, true);
+        assert_eq!(event.value(), None);
+        assert_eq!(event.with_value(3), event);
+    }
+
+    #[test]
+    fn map_value_keeps_variant() {
+        let others = [
+            Event::Disconnect, Event::Button(Button::Number(3), true), Event::Hat(Hat::Pov, Direction::UpLeft),
+            Event::Repeat(30), Event::Click(true), Event::Bell(false),
+        ];
+        for event in valued().chain(others) {
+            let mapped = event.map_value(|value| value as f64 / 2.0);
+            assert_eq!(mapped.value().copied(), event.value().map(|&value| value as f64 / 2.0), "{event:?}");
+            assert_eq!(mapped.map_value(|value| (value * 2.0) as i32), event);
+            assert_eq!(std::mem::discriminant(&mapped.map_value(|value| value as i32)), std::mem::discriminant(&event));
+        }
+    }
+
+    /// Pins the serialized form of `value` to `json`, both ways.
+    #[cfg(feature = "serde")]
+    fn assert_json<V>(value: V, json: &str) where V: serde::Serialize + serde::de::DeserializeOwned + PartialEq + std::fmt::Debug {
+        assert_eq!(serde_json::to_string(&value).unwrap(), json);
+        assert_eq!(serde_json::from_str::<V>(json).unwrap(), value);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn event_json() {
+        assert_json(Event::<i32>::Disconnect, r#""Disconnect""#);
+        assert_json(Event::<i32>::Button(Button::ActionA, true), r#"{"Button":["ActionA",true]}"#);
+        assert_json(Event::<i32>::Button(Button::Number(3), true), r#"{"Button":[{"Number":3},true]}"#);
+        assert_json(Event::Axis(Axis::JoyX, -512), r#"{"Axis":["JoyX",-512]}"#);
+        assert_json(Event::Axis(Axis::JoyX, 0.5), r#"{"Axis":["JoyX",0.5]}"#);
+        assert_json(Event::<i32>::Hat(Hat::Pov, Direction::UpLeft), r#"{"Hat":["Pov","UpLeft"]}"#);
+        assert_json(Event::<i32>::Repeat(0x130), r#"{"Repeat":304}"#);
+        assert_json(Event::Power(0x74, 1), r#"{"Power":[116,1]}"#);
+        assert_json(Event::Tone(440), r#"{"Tone":440}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn flat_event_json() {
+        assert_json(flat::Event::<i32>::Disconnect, r#""Disconnect""#);
+        assert_json(flat::Event::<i32>::ActionA(true), r#"{"ActionA":true}"#);
+        assert_json(flat::Event::JoyX(-512), r#"{"JoyX":-512}"#);
+        assert_json(flat::Event::<i32>::Number(3, true), r#"{"Number":[3,true]}"#);
+        assert_json(flat::Event::<i32>::PovUp(false), r#"{"PovUp":false}"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn identity_json() {
+        let identity = Identity {
+            name: "Pad".to_owned(),
+            phys: Some("usb-1".to_owned()),
+            uniq: None,
+            id: InputId { bustype: 3, vendor: 0x045e, product: 0x028e, version: 0x0114 },
+        };
+        assert_json(
+            identity,
+            r#"{"name":"Pad","phys":"usb-1","uniq":null,"id":{"bustype":3,"vendor":1118,"product":654,"version":276}}"#,
+        );
+        assert_json(
+            StableId::Serial { vendor: 0x045e, product: 0x028e, uniq: "abc".to_owned(), node: 1 },
+            r#"{"Serial":{"vendor":1118,"product":654,"uniq":"abc","node":1}}"#,
+        );
+        assert_json(StableId::Phys("usb-1".to_owned()), r#"{"Phys":"usb-1"}"#);
+        assert_json(
+            StableId::Model { bustype: 3, vendor: 0x045e, product: 0x028e, index: 0 },
+            r#"{"Model":{"bustype":3,"vendor":1118,"product":654,"index":0}}"#,
+        );
+    }
 }
@@ -0,0 +1,341 @@
+//! Notifications of input devices being plugged in and removed.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+
+use input_linux_sys::*;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use crate::device::bit_is_set;
+use crate::{Device, Identity, InputProp};
+
+/// Broad kind of an input device, derived from its capabilities the way
+/// udev's `input_id` builtin does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceClass {
+    Joystick,
+    Keyboard,
+    Mouse,
+    Touchpad,
+    Tablet,
+    /// Accelerometer and gyroscope of a gamepad, see [`crate::motion`].
+    Motion,
+}
+
+/// What is known about a device node.  Details that couldn't be queried,
+/// e.g. because the node is not an evdev device, are left empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceInfo {
+    pub path: PathBuf,
+    pub identity: Identity,
+    pub classes: Vec<DeviceClass>,
+}
+
+impl DeviceInfo {
+    /// Query an open device.
+    pub fn from_device(path: impl Into<PathBuf>, device: &Device) -> io::Result<Self> {
+        Ok(Self {
+            path: path.into(),
+            identity: device.identity()?,
+            classes: classify(device)?,
+        })
+    }
+
+    /// Open and query a device node.  A node that is not an evdev device
+    /// gives empty details; other errors, e.g. missing permissions, are
+    /// returned.
+    pub fn probe(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let device = Device::open(&path)?;
+        match Self::from_device(path.clone(), &device) {
+            Err(err) if is_not_evdev(&err) => Ok(Self { path, identity: Identity::default(), classes: Vec::new() }),
+            result => result,
+        }
+    }
+
+    pub fn is(&self, class: DeviceClass) -> bool {
+        self.classes.contains(&class)
+    }
+}
+
+/// The error of an evdev ioctl on something else.
+fn is_not_evdev(err: &io::Error) -> bool {
+    matches!(err.raw_os_error(), Some(nix::libc::ENOTTY | nix::libc::EINVAL))
+}
+
+fn classify(device: &Device) -> io::Result<Vec<DeviceClass>> {
+    let keys = device.bits(EV_KEY as _)?;
+    let rel = device.bits(EV_REL as _)?;
+    let abs = device.bits(EV_ABS as _)?;
+    let props = device.props()?;
+    let key = |code: i32| bit_is_set(&keys, code as _);
+    let has_xy = |mask: &[u8], x: i32, y: i32| bit_is_set(mask, x as _) && bit_is_set(mask, y as _);
+
+    let mut classes = Vec::new();
+    let joystick_buttons = (BTN_JOYSTICK..BTN_JOYSTICK + 16).chain(BTN_GAMEPAD..BTN_GAMEPAD + 16).any(key);
+    if joystick_buttons || (has_xy(&abs, ABS_X, ABS_Y) && (key(BTN_TRIGGER) || bit_is_set(&abs, ABS_THROTTLE as _))) {
+        classes.push(DeviceClass::Joystick);
+    }
+    // udev requires the whole block of keys from Esc to D.
+    if (KEY_ESC..=KEY_D).all(key) {
+        classes.push(DeviceClass::Keyboard);
+    }
+    if has_xy(&rel, REL_X, REL_Y) && key(BTN_MOUSE) {
+        classes.push(DeviceClass::Mouse);
+    }
+    if has_xy(&abs, ABS_X, ABS_Y) && key(BTN_TOOL_FINGER) && !key(BTN_TOOL_PEN)
+        && !props.contains(InputProp::Direct)
+    {
+        classes.push(DeviceClass::Touchpad);
+    }
+    if has_xy(&abs, ABS_X, ABS_Y) && (key(BTN_TOOL_PEN) || key(BTN_STYLUS)) {
+        classes.push(DeviceClass::Tablet);
+    }
+    if props.contains(InputProp::Accelerometer) {
+        classes.push(DeviceClass::Motion);
+    }
+    Ok(classes)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorEvent {
+    Added(DeviceInfo),
+    Removed(PathBuf),
+}
+
+/// Watches a device directory (`/dev/input` by default) for `eventN` nodes
+/// coming and going.
+#[derive(Debug)]
+pub struct DeviceMonitor {
+    inotify: Inotify,
+    dir: PathBuf,
+    classes: Vec<DeviceClass>,
+    known: HashSet<PathBuf>,
+    queue: Vec<MonitorEvent>,
+}
+
+impl DeviceMonitor {
+    pub fn new() -> io::Result<Self> {
+        Self::watch("/dev/input")
+    }
+
+    /// Watch another directory, e.g. a temporary one in tests.
+    pub fn watch(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        let inotify = Inotify::init(InitFlags::IN_CLOEXEC).map_err(io::Error::from)?;
+        let monitor = Self { inotify, dir, classes: Vec::new(), known: HashSet::new(), queue: Vec::new() };
+        // IN_ATTRIB catches udev fixing up permissions after creation.
+        let flags = AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB | AddWatchFlags::IN_DELETE
+            | AddWatchFlags::IN_MOVED_TO | AddWatchFlags::IN_MOVED_FROM;
+        monitor.inotify.add_watch(&monitor.dir, flags).map_err(io::Error::from)?;
+        Ok(monitor)
+    }
+
+    /// Only report devices of one of these classes.  An empty filter, the
+    /// default, reports every device.
+    pub fn filter(mut self, classes: &[DeviceClass]) -> Self {
+        self.classes = classes.to_vec();
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Devices already present, reported as added.  Devices reported here
+    /// are reported as removed once they go away.
+    pub fn existing(&mut self) -> io::Result<Vec<DeviceInfo>> {
+        let mut names: Vec<_> = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name())
+            .filter(|name| is_event_node(Path::new(name)))
+            .collect();
+        names.sort();
+        let mut out = Vec::new();
+        for name in names {
+            if let Some(MonitorEvent::Added(info)) = self.added(self.dir.join(name)) {
+                out.push(info);
+            }
+        }
+        Ok(out)
+    }
+
+    /// Block until the next device is added or removed.
+    pub fn next_event(&mut self) -> io::Result<MonitorEvent> {
+        loop {
+            if !self.queue.is_empty() {
+                return Ok(self.queue.remove(0));
+            }
+            for event in self.inotify.read_events().map_err(io::Error::from)? {
+                let Some(name) = event.name else {
+                    continue;
+                };
+                let path = self.dir.join(name);
+                if !is_event_node(&path) {
+                    continue;
+                }
+                let gone = event.mask.intersects(AddWatchFlags::IN_DELETE | AddWatchFlags::IN_MOVED_FROM);
+                let change = if gone {
+                    self.known.remove(&path).then_some(MonitorEvent::Removed(path))
+                } else if self.known.contains(&path) {
+                    None
+                } else {
+                    self.added(path)
+                };
+                self.queue.extend(change);
+            }
+        }
+    }
+
+    fn added(&mut self, path: PathBuf) -> Option<MonitorEvent> {
+        // Not accessible yet, udev will change the permissions shortly.
+        let info = DeviceInfo::probe(&path).ok()?;
+        if !self.classes.is_empty() && !self.classes.iter().any(|class| info.is(*class)) {
+            return None;
+        }
+        self.known.insert(path);
+        Some(MonitorEvent::Added(info))
+    }
+}
+
+impl Iterator for DeviceMonitor {
+    type Item = io::Result<MonitorEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_event())
+    }
+}
+
+impl AsRawFd for DeviceMonitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inotify.as_raw_fd()
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.inotify.as_raw_fd());
+    }
+}
+
+fn is_event_node(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix("event"))
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("linux-input-event-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir(&path).unwrap();
+            Self(path)
+        }
+
+        fn create(&self, name: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::File::create(&path).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn added_path(event: MonitorEvent) -> PathBuf {
+        match event {
+            MonitorEvent::Added(info) => info.path,
+            other => panic!("expected Added, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let dir = TempDir::new("monitor");
+        let mut monitor = DeviceMonitor::watch(&dir.0).unwrap();
+        assert_eq!(monitor.dir(), dir.0);
+
+        // Not event nodes.
+        dir.create("js0");
+        dir.create("event");
+        let event3 = dir.create("event3");
+        let event10 = dir.create("event10");
+        assert_eq!(added_path(monitor.next_event().unwrap()), event3);
+        assert_eq!(added_path(monitor.next_event().unwrap()), event10);
+
+        fs::remove_file(&event3).unwrap();
+        assert_eq!(monitor.next_event().unwrap(), MonitorEvent::Removed(event3));
+
+        // Renamed into place, as udev does, and away again.
+        let staged = dir.create(".event4.tmp");
+        let event4 = dir.0.join("event4");
+        fs::rename(&staged, &event4).unwrap();
+        assert_eq!(added_path(monitor.next_event().unwrap()), event4);
+        fs::rename(&event4, &staged).unwrap();
+        assert_eq!(monitor.next_event().unwrap(), MonitorEvent::Removed(event4));
+    }
+
+    #[test]
+    fn existing_nodes() {
+        let dir = TempDir::new("existing");
+        let event2 = dir.create("event2");
+        let event1 = dir.create("event1");
+        dir.create("mouse0");
+        let mut monitor = DeviceMonitor::watch(&dir.0).unwrap();
+        // Nodes that aren't evdev devices are reported without details.
+        let paths: Vec<_> = monitor.existing().unwrap().into_iter().map(|info| info.path).collect();
+        assert_eq!(paths, [event1.clone(), event2]);
+        fs::remove_file(&event1).unwrap();
+        assert_eq!(monitor.next_event().unwrap(), MonitorEvent::Removed(event1));
+    }
+
+    #[test]
+    fn class_filter() {
+        let dir = TempDir::new("filter");
+        let event0 = dir.create("event0");
+        let mut monitor = DeviceMonitor::watch(&dir.0).unwrap().filter(&[DeviceClass::Joystick]);
+        // Plain files have no classes.
+        assert!(monitor.existing().unwrap().is_empty());
+        assert_eq!(monitor.added(event0.clone()), None);
+
+        let mut monitor = monitor.filter(&[]);
+        assert!(matches!(monitor.added(event0.clone()), Some(MonitorEvent::Added(_))));
+
+        // Removal of a node that was never reported is not reported.
+        let mut monitor = DeviceMonitor::watch(&dir.0).unwrap().filter(&[DeviceClass::Joystick]);
+        fs::remove_file(&event0).unwrap();
+        monitor.classes.clear();
+        let event1 = dir.create("event1");
+        assert_eq!(added_path(monitor.next_event().unwrap()), event1);
+    }
+
+    #[test]
+    fn probe_errors() {
+        let dir = TempDir::new("probe");
+        let event0 = dir.create("event0");
+        let info = DeviceInfo::probe(&event0).unwrap();
+        assert_eq!((info.path, info.identity, info.classes), (event0, Identity::default(), Vec::new()));
+        assert!(DeviceInfo::probe(dir.0.join("event1")).is_err());
+
+        assert!(is_not_evdev(&io::Error::from_raw_os_error(nix::libc::ENOTTY)));
+        assert!(is_not_evdev(&io::Error::from_raw_os_error(nix::libc::EINVAL)));
+        assert!(!is_not_evdev(&io::Error::from_raw_os_error(nix::libc::EACCES)));
+        assert!(!is_not_evdev(&io::Error::from_raw_os_error(nix::libc::ENODEV)));
+    }
+}
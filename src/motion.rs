@@ -0,0 +1,281 @@
+//! Motion sensors of gamepads.
+//!
+//! DualShock 4, DualSense, Switch Pro and Joy-Con drivers expose the
+//! accelerometer and gyroscope as a separate node with
+//! `INPUT_PROP_ACCELEROMETER`, reusing `ABS_X/Y/Z` for acceleration and
+//! `ABS_RX/RY/RZ` for angular velocity.  [`MotionDecoder`] reads those in
+//! physical units instead of as stick positions, and [`pair`] finds the
+//! gamepad node a motion node belongs to.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::path::Path;
+
+use input_linux_sys::*;
+
+use crate::device::is_disconnect;
+use crate::{AbsInfo, Device, DeviceClass, DeviceInfo, InputProp};
+
+/// Standard gravity in m/s².
+pub const STANDARD_GRAVITY: f32 = 9.80665;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MotionEvent {
+    /// Acceleration in m/s², including gravity.
+    Accel { x: f32, y: f32, z: f32 },
+    /// Angular velocity in degrees per second.
+    Gyro { pitch: f32, yaw: f32, roll: f32 },
+    Disconnect,
+}
+
+/// Whether a device is a motion node (`INPUT_PROP_ACCELEROMETER`).
+pub fn is_motion(device: &Device) -> io::Result<bool> {
+    Ok(device.props()?.contains(InputProp::Accelerometer))
+}
+
+/// The gamepad a motion node belongs to, among `devices`.
+///
+/// Drivers give both nodes the same unique ID, usually the Bluetooth
+/// address, and physical path, and name the motion node after the gamepad
+/// (`... Motion Sensors`, `... IMU`).  The first match in that order wins;
+/// the model alone never matches, as several identical gamepads are common.
+pub fn pair<'a>(motion: &DeviceInfo, devices: &'a [DeviceInfo]) -> Option<&'a DeviceInfo> {
+    let motion_id = &motion.identity;
+    let gamepads: Vec<_> = devices.iter()
+        .filter(|device| device.path != motion.path && device.is(DeviceClass::Joystick))
+        .filter(|device| device.identity.id.vendor == motion_id.id.vendor && device.identity.id.product == motion_id.id.product)
+        .collect();
+    let same = |value: &Option<String>, other: &Option<String>| value.is_some() && value == other;
+    gamepads.iter().find(|device| same(&device.identity.uniq, &motion_id.uniq))
+        .or_else(|| gamepads.iter().find(|device| same(&device.identity.phys, &motion_id.phys)))
+        .or_else(|| {
+            let mut named = gamepads.iter().filter(|device| motion_id.name.starts_with(&device.identity.name));
+            named.next().filter(|_| named.next().is_none())
+        })
+        .copied()
+}
+
+/// Decodes the raw events of a motion node a frame at a time.
+///
+/// Values are scaled by the resolution of each axis, which the kernel
+/// defines in units per g for acceleration and units per degree per second
+/// for angular velocity, and acceleration is converted to m/s².  Axes
+/// without a resolution are reported raw.
+#[derive(Debug, Clone)]
+pub struct MotionDecoder {
+    axes: BTreeMap<u16, AbsInfo>,
+    /// Raw values of `ABS_X..=ABS_RZ`.
+    values: [i32; 6],
+    accelerated: bool,
+    rotated: bool,
+    dropped: bool,
+}
+
+impl MotionDecoder {
+    /// Decode a motion node with the given `ABS_*` axis ranges.
+    pub fn new(axes: impl IntoIterator<Item = (u16, AbsInfo)>) -> Self {
+        let axes: BTreeMap<_, _> = axes.into_iter().collect();
+        let mut values = [0; 6];
+        for (code, value) in (ABS_X as u16..).zip(&mut values) {
+            *value = axes.get(&code).map_or(0, |info| info.value);
+        }
+        Self { axes, values, accelerated: false, rotated: false, dropped: false }
+    }
+
+    pub fn for_device(device: &Device) -> io::Result<Self> {
+        let mut axes = Vec::new();
+        for code in ABS_X..=ABS_RZ {
+            if device.has(EV_ABS as _, code as _)? {
+                axes.push((code as u16, device.absinfo(code as _)?));
+            }
+        }
+        Ok(Self::new(axes))
+    }
+
+    /// Current acceleration, as in [`MotionEvent::Accel`].
+    pub fn accel(&self) -> MotionEvent {
+        let [x, y, z] = [0, 1, 2].map(|index| self.scaled(index, STANDARD_GRAVITY));
+        MotionEvent::Accel { x, y, z }
+    }
+
+    /// Current angular velocity, as in [`MotionEvent::Gyro`].
+    pub fn gyro(&self) -> MotionEvent {
+        let [pitch, yaw, roll] = [3, 4, 5].map(|index| self.scaled(index, 1.0));
+        MotionEvent::Gyro { pitch, yaw, roll }
+    }
+
+    /// Handle one raw event, returning the events of the frame it ends.
+    pub fn feed(&mut self, raw: &input_event) -> Vec<MotionEvent> {
+        match (raw.type_ as i32, raw.code as i32) {
+            (EV_SYN, SYN_REPORT) if self.dropped => self.dropped = false,
+            (EV_SYN, SYN_REPORT) => {
+                let mut frame = Vec::new();
+                if std::mem::take(&mut self.accelerated) {
+                    frame.push(self.accel());
+                }
+                if std::mem::take(&mut self.rotated) {
+                    frame.push(self.gyro());
+                }
+                return frame;
+            }
+            (EV_SYN, SYN_DROPPED) => {
+                (self.accelerated, self.rotated) = (false, false);
+                self.dropped = true;
+            }
+            _ if self.dropped => {}
+            (EV_ABS, code @ ABS_X..=ABS_RZ) => {
+                self.values[code as usize] = raw.value;
+                if code <= ABS_Z {
+                    self.accelerated = true;
+                } else {
+                    self.rotated = true;
+                }
+            }
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    /// Value in resolution units times `unit`.
+    fn scaled(&self, index: usize, unit: f32) -> f32 {
+        let value = self.values[index] as f32;
+        match self.axes.get(&(index as u16)) {
+            Some(info) if info.resolution > 0 => value / info.resolution as f32 * unit,
+            _ => value,
+        }
+    }
+}
+
+/// A motion node, read through a [`MotionDecoder`].
+#[derive(Debug)]
+pub struct Motion {
+    device: Device,
+    decoder: MotionDecoder,
+    queue: VecDeque<MotionEvent>,
+    disconnected: bool,
+}
+
+impl Motion {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(Device::open(path)?)
+    }
+
+    /// Fails with `InvalidInput` if the device is not a motion node.
+    pub fn new(device: Device) -> io::Result<Self> {
+        if !is_motion(&device)? {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a motion sensor device"));
+        }
+        let decoder = MotionDecoder::for_device(&device)?;
+        Ok(Self { device, decoder, queue: VecDeque::new(), disconnected: false })
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn decoder(&self) -> &MotionDecoder {
+        &self.decoder
+    }
+
+    /// Block until the next event.  Once the device goes away this returns
+    /// [`MotionEvent::Disconnect`] a single time, and errors afterwards.
+    pub fn next_event(&mut self) -> io::Result<MotionEvent> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Ok(event);
+            }
+            if self.disconnected {
+                return Err(io::Error::from_raw_os_error(nix::libc::ENODEV));
+            }
+            match self.device.read_event() {
+                Ok(raw) => self.queue.extend(self.decoder.feed(&raw)),
+                Err(err) if is_disconnect(&err) => {
+                    self.disconnected = true;
+                    return Ok(MotionEvent::Disconnect);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use input_linux_sys::timeval;
+
+    use super::*;
+    use crate::{Identity, InputId, StableId};
+
+    fn raw(type_: i32, code: i32, value: i32) -> input_event {
+        input_event { time: timeval { tv_sec: 0, tv_usec: 0 }, type_: type_ as u16, code: code as u16, value }
+    }
+
+    #[test]
+    fn decoder_units() {
+        let accel = AbsInfo { minimum: -32768, maximum: 32767, resolution: 8192, ..AbsInfo::default() };
+        let gyro = AbsInfo { minimum: -1048576, maximum: 1048576, resolution: 1024, ..AbsInfo::default() };
+        let mut decoder = MotionDecoder::new([
+            (ABS_X as u16, accel), (ABS_Y as u16, accel), (ABS_Z as u16, AbsInfo { resolution: 0, ..accel }),
+            (ABS_RX as u16, gyro), (ABS_RY as u16, gyro), (ABS_RZ as u16, gyro),
+        ]);
+        let values = [(ABS_X, 8192), (ABS_Y, -4096), (ABS_Z, 100), (ABS_RX, 1024), (ABS_RY, 0), (ABS_RZ, -90 * 1024)];
+        for (code, value) in values {
+            assert!(decoder.feed(&raw(EV_ABS, code, value)).is_empty());
+        }
+        assert_eq!(decoder.feed(&raw(EV_SYN, SYN_REPORT, 0)), [
+            MotionEvent::Accel { x: STANDARD_GRAVITY, y: -STANDARD_GRAVITY / 2.0, z: 100.0 },
+            MotionEvent::Gyro { pitch: 1.0, yaw: 0.0, roll: -90.0 },
+        ]);
+        assert!(decoder.feed(&raw(EV_SYN, SYN_REPORT, 0)).is_empty());
+        decoder.feed(&raw(EV_ABS, ABS_RY, 2048));
+        assert_eq!(decoder.feed(&raw(EV_SYN, SYN_REPORT, 0)), [MotionEvent::Gyro { pitch: 1.0, yaw: 2.0, roll: -90.0 }]);
+    }
+
+    fn info(path: &str, name: &str, uniq: Option<&str>) -> DeviceInfo {
+        let identity = Identity {
+            name: name.to_owned(),
+            phys: Some("usb-0000:00:14.0-2/input3".to_owned()),
+            uniq: uniq.map(str::to_owned),
+            id: InputId { bustype: 0x05, vendor: 0x054c, product: 0x09cc, version: 0x8100 },
+        };
+        DeviceInfo { path: PathBuf::from(path), identity, classes: vec![DeviceClass::Joystick] }
+    }
+
+    #[test]
+    fn pair_by_uniq() {
+        let devices = [
+            info("/dev/input/event10", "Wireless Controller", Some("a4:5e:60:00:00:01")),
+            info("/dev/input/event11", "Wireless Controller Motion Sensors", Some("a4:5e:60:00:00:01")),
+            info("/dev/input/event12", "Wireless Controller", Some("a4:5e:60:00:00:02")),
+            info("/dev/input/event13", "Wireless Controller Motion Sensors", Some("a4:5e:60:00:00:02")),
+        ];
+        assert_eq!(pair(&devices[1], &devices).map(|device| &device.path), Some(&devices[0].path));
+        assert_eq!(pair(&devices[3], &devices).map(|device| &device.path), Some(&devices[2].path));
+
+        // Both nodes of each pad keep their serial.
+        let ids = StableId::assign(&devices.map(|device| (device.path, device.identity)));
+        let nodes: Vec<_> = ids.iter().map(|id| match id {
+            StableId::Serial { node, .. } => Some(*node),
+            _ => None,
+        }).collect();
+        assert_eq!(nodes, [Some(0), Some(1), Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn pair_by_name() {
+        let mut pad = info("/dev/input/event10", "Pro Controller", None);
+        let mut imu = info("/dev/input/event11", "Pro Controller (IMU)", None);
+        pad.identity.phys = None;
+        imu.identity.phys = None;
+        let devices = [pad.clone(), imu.clone()];
+        assert_eq!(pair(&imu, &devices).map(|device| &device.path), Some(&pad.path));
+
+        // Two identical pads can't be told apart by name or model.
+        let mut other = pad.clone();
+        other.path = PathBuf::from("/dev/input/event12");
+        assert_eq!(pair(&imu, &[pad, imu.clone(), other]), None);
+    }
+}
@@ -0,0 +1,358 @@
+//! Composable event processing.
+//!
+//! An [`EventPipeline`] is a chain of [`Stage`]s, each turning one event into
+//! zero or more events.  Pipelines are push based: [`EventPipeline::process`]
+//! can be called from any event loop, [`EventPipeline::run`] adapts an
+//! iterator and, with the `stream` feature, [`EventPipeline::run_stream`]
+//! adapts an async stream.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::Event;
+
+/// One step of an [`EventPipeline`].
+pub trait Stage<T> where T: TryFrom<i32> {
+    /// Handle one event, pushing the resulting events to `out`.
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>);
+}
+
+/// Keeps the events matching a predicate.
+#[derive(Debug, Clone)]
+pub struct Filter<F>(pub F);
+
+impl<T, F> Stage<T> for Filter<F> where T: TryFrom<i32>, F: FnMut(&Event<T>) -> bool {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        if (self.0)(&event) {
+            out.push(event);
+        }
+    }
+}
+
+/// Replaces each event, or drops it by returning `None`.
+#[derive(Debug, Clone)]
+pub struct Map<F>(pub F);
+
+impl<T, F> Stage<T> for Map<F> where T: TryFrom<i32>, F: FnMut(Event<T>) -> Option<Event<T>> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        out.extend((self.0)(event));
+    }
+}
+
+/// Stage with its own state, free to emit any number of events.
+#[derive(Debug, Clone)]
+pub struct Transform<S, F> {
+    pub state: S,
+    pub f: F,
+}
+
+impl<T, S, F> Stage<T> for Transform<S, F>
+where
+    T: TryFrom<i32>,
+    F: FnMut(&mut S, Event<T>, &mut Vec<Event<T>>),
+{
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        (self.f)(&mut self.state, event, out)
+    }
+}
+
+/// Sends every event through each branch, emitting the outputs of all
+/// branches in order.
+pub struct FanOut<T> where T: TryFrom<i32> {
+    branches: Vec<EventPipeline<T>>,
+}
+
+impl<T> FanOut<T> where T: TryFrom<i32> {
+    pub fn new(branches: Vec<EventPipeline<T>>) -> Self {
+        Self { branches }
+    }
+}
+
+impl<T> Stage<T> for FanOut<T> where T: TryFrom<i32> + Clone {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        for branch in &mut self.branches {
+            branch.process_into(event.clone(), out);
+        }
+    }
+}
+
+impl<T> fmt::Debug for FanOut<T> where T: TryFrom<i32> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanOut").field("branches", &self.branches).finish()
+    }
+}
+
+/// A chain of [`Stage`]s.  An empty pipeline passes events through.
+///
+/// Stages may hold non-`Send` state such as `Rc`s, so a pipeline stays on
+/// the thread that built it.
+pub struct EventPipeline<T = i32> where T: TryFrom<i32> {
+    stages: Vec<Box<dyn Stage<T>>>,
+    scratch: Vec<Event<T>>,
+}
+
+impl<T> EventPipeline<T> where T: TryFrom<i32> {
+    pub fn new() -> Self {
+        Self { stages: Vec::new(), scratch: Vec::new() }
+    }
+
+    /// Append a stage.
+    pub fn stage(mut self, stage: impl Stage<T> + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    pub fn filter(self, f: impl FnMut(&Event<T>) -> bool + 'static) -> Self {
+        self.stage(Filter(f))
+    }
+
+    pub fn map(self, f: impl FnMut(Event<T>) -> Option<Event<T>> + 'static) -> Self {
+        self.stage(Map(f))
+    }
+
+    pub fn transform<S: 'static>(
+        self,
+        state: S,
+        f: impl FnMut(&mut S, Event<T>, &mut Vec<Event<T>>) + 'static,
+    ) -> Self {
+        self.stage(Transform { state, f })
+    }
+
+    pub fn fan_out(self, branches: Vec<EventPipeline<T>>) -> Self where T: Clone + 'static {
+        self.stage(FanOut::new(branches))
+    }
+
+    /// Run one event through all stages, pushing the results to `out`.
+    pub fn process_into(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        let start = out.len();
+        out.push(event);
+        for stage in &mut self.stages {
+            self.scratch.extend(out.drain(start..));
+            for event in self.scratch.drain(..) {
+                stage.process(event, out);
+            }
+        }
+    }
+
+    /// Run one event through all stages.
+    pub fn process(&mut self, event: Event<T>) -> Vec<Event<T>> {
+        let mut out = Vec::new();
+        self.process_into(event, &mut out);
+        out
+    }
+
+    /// Adapt an iterator of events.
+    pub fn run<I>(self, events: I) -> Pipe<T, I::IntoIter> where I: IntoIterator<Item = Event<T>> {
+        Pipe { pipeline: self, input: events.into_iter(), pending: VecDeque::new(), buffer: Vec::new() }
+    }
+
+    /// Adapt an async stream of events.
+    #[cfg(feature = "stream")]
+    pub fn run_stream<S>(self, events: S) -> Pipe<T, S>
+    where
+        S: futures_core::Stream<Item = Event<T>> + Unpin,
+    {
+        Pipe { pipeline: self, input: events, pending: VecDeque::new(), buffer: Vec::new() }
+    }
+}
+
+impl<T> Default for EventPipeline<T> where T: TryFrom<i32> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Stage<T> for EventPipeline<T> where T: TryFrom<i32> {
+    fn process(&mut self, event: Event<T>, out: &mut Vec<Event<T>>) {
+        self.process_into(event, out)
+    }
+}
+
+impl<T> fmt::Debug for EventPipeline<T> where T: TryFrom<i32> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventPipeline").field("stages", &self.stages.len()).finish()
+    }
+}
+
+/// Events of an iterator or stream after passing through a pipeline.
+pub struct Pipe<T, I> where T: TryFrom<i32> {
+    pipeline: EventPipeline<T>,
+    input: I,
+    pending: VecDeque<Event<T>>,
+    buffer: Vec<Event<T>>,
+}
+
+impl<T, I> Pipe<T, I> where T: TryFrom<i32> {
+    pub fn pipeline(&self) -> &EventPipeline<T> {
+        &self.pipeline
+    }
+
+    pub fn into_inner(self) -> I {
+        self.input
+    }
+
+    fn push(&mut self, event: Event<T>) {
+        self.pipeline.process_into(event, &mut self.buffer);
+        self.pending.extend(self.buffer.drain(..));
+    }
+}
+
+impl<T, I> Iterator for Pipe<T, I> where T: TryFrom<i32>, I: Iterator<Item = Event<T>> {
+    type Item = Event<T>;
+
+    fn next(&mut self) -> Option<Event<T>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            let event = self.input.next()?;
+            self.push(event);
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+impl<T, S> futures_core::Stream for Pipe<T, S>
+where
+    T: TryFrom<i32> + Unpin,
+    S: futures_core::Stream<Item = Event<T>> + Unpin,
+{
+    type Item = Event<T>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Event<T>>> {
+        use std::task::Poll;
+
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            match std::pin::Pin::new(&mut this.input).poll_next(cx) {
+                Poll::Ready(Some(event)) => this.push(event),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<T, I> fmt::Debug for Pipe<T, I> where T: TryFrom<i32> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipe").field("pipeline", &self.pipeline).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::{Axis, Button};
+
+    fn button(button: Button) -> Event {
+        Event::Button(button, true)
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut pipeline = EventPipeline::new()
+            .map(|event| match event {
+                Event::Axis(axis, value) => Some(Event::Axis(axis, value * 2)),
+                event => Some(event),
+            })
+            .filter(|event| !matches!(event, Event::Axis(_, value) if *value > 10))
+            .map(|event| match event {
+                Event::Axis(axis, value) => Some(Event::Axis(axis, value + 1)),
+                event => Some(event),
+            });
+        assert_eq!(pipeline.process(Event::Axis(Axis::JoyX, 4)), [Event::Axis(Axis::JoyX, 9)]);
+        assert_eq!(pipeline.process(Event::Axis(Axis::JoyX, 6)), []);
+        assert_eq!(pipeline.process(button(Button::ActionA)), [button(Button::ActionA)]);
+    }
+
+    #[test]
+    fn transform_keeps_state() {
+        let mut pipeline = EventPipeline::new().transform(0, |count: &mut i32, event, out: &mut Vec<Event>| {
+            *count += 1;
+            for _ in 0..*count {
+                out.push(event);
+            }
+        });
+        assert_eq!(pipeline.process(button(Button::ActionA)).len(), 1);
+        assert_eq!(pipeline.process(button(Button::ActionA)).len(), 2);
+    }
+
+    #[test]
+    fn fan_out_runs_every_branch() {
+        let rename = |to| EventPipeline::new().map(move |_| Some(button(to)));
+        let branches = vec![rename(Button::ActionA), EventPipeline::new(), rename(Button::ActionB)];
+        let mut pipeline = EventPipeline::new()
+            .fan_out(branches)
+            .stage(Transform { state: (), f: |_: &mut (), event, out: &mut Vec<Event>| out.extend([event, event]) });
+        assert_eq!(pipeline.process(button(Button::Exit)), [
+            button(Button::ActionA),
+            button(Button::ActionA),
+            button(Button::Exit),
+            button(Button::Exit),
+            button(Button::ActionB),
+            button(Button::ActionB),
+        ]);
+    }
+
+    #[test]
+    fn stages_may_share_rc_state() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = seen.clone();
+        let mut pipeline = EventPipeline::new().filter(move |event| {
+            log.borrow_mut().push(*event);
+            true
+        });
+        pipeline.process(button(Button::ActionA));
+        assert_eq!(*seen.borrow(), [button(Button::ActionA)]);
+    }
+
+    #[test]
+    fn iterator_flushes_every_output() {
+        let pipeline = EventPipeline::new()
+            .map(|event| (event != button(Button::ActionB)).then_some(event))
+            .transform((), |_, event, out| out.extend([event, Event::Disconnect]));
+        let events = pipeline.run([button(Button::ActionA), button(Button::ActionB), button(Button::ActionC)]);
+        assert_eq!(events.collect::<Vec<_>>(), [
+            button(Button::ActionA),
+            Event::Disconnect,
+            button(Button::ActionC),
+            Event::Disconnect,
+        ]);
+    }
+
+    #[cfg(feature = "stream")]
+    #[test]
+    fn stream_flushes_every_output() {
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Waker};
+
+        struct Ready(std::vec::IntoIter<Event>);
+
+        impl futures_core::Stream for Ready {
+            type Item = Event;
+
+            fn poll_next(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Event>> {
+                Poll::Ready(self.get_mut().0.next())
+            }
+        }
+
+        let pipeline = EventPipeline::new().transform((), |_, event, out| out.extend([event, event]));
+        let mut stream = pipeline.run_stream(Ready(vec![button(Button::ActionA), button(Button::ActionB)].into_iter()));
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut events = Vec::new();
+        while let Poll::Ready(Some(event)) = futures_core::Stream::poll_next(Pin::new(&mut stream), &mut cx) {
+            events.push(event);
+        }
+        let (a, b) = (button(Button::ActionA), button(Button::ActionB));
+        assert_eq!(events, [a, a, b, b]);
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::time::{Clock, SystemClock, Timestamp};
+use crate::{Button, Event};
+
+/// Shortest period between repeats, whatever the configuration.
+pub const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+/// Per button software repeat settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepeatConfig {
+    /// Time a button has to be held before the first repeat.
+    pub delay: Duration,
+    /// Time between repeats.
+    pub period: Duration,
+    /// Optional speed up while the button stays held.
+    pub acceleration: Option<Acceleration>,
+}
+
+/// Geometric repeat acceleration: every repeat multiplies the period by
+/// `factor`, never going below `min_period`.  `factor` is clamped to
+/// `0.0..=1.0`, as repeats only ever speed up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Acceleration {
+    pub factor: f32,
+    pub min_period: Duration,
+}
+
+impl RepeatConfig {
+    pub const fn new(delay: Duration, period: Duration) -> Self {
+        Self { delay, period, acceleration: None }
+    }
+
+    pub const fn accelerate(mut self, factor: f32, min_period: Duration) -> Self {
+        self.acceleration = Some(Acceleration { factor, min_period });
+        self
+    }
+
+    /// Period between repeat `count` and `count + 1`, at least
+    /// [`MIN_PERIOD`] so polling always catches up.
+    fn period(&self, count: u32) -> Duration {
+        let period = match self.acceleration {
+            None => self.period,
+            Some(Acceleration { factor, min_period }) => {
+                let factor = if factor.is_nan() { 1.0 } else { factor.clamp(0.0, 1.0) };
+                let scale = factor.powi(count.min(i32::MAX as u32) as i32);
+                self.period.mul_f64(scale as f64).max(min_period)
+            }
+        };
+        period.max(MIN_PERIOD)
+    }
+}
+
+impl Default for RepeatConfig {
+    /// Kernel keyboard defaults: 250ms delay, 33ms period.
+    fn default() -> Self {
+        Self::new(Duration::from_millis(250), Duration::from_millis(33))
+    }
+}
+
+/// A synthesized repeat of a held button, kept apart from the button's
+/// real events so it can't be mistaken for a second press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RepeatEvent {
+    pub button: Button,
+    /// Number of the repeat since the press, from 1.
+    pub count: u32,
+}
+
+#[derive(Debug)]
+struct Held {
+    config: RepeatConfig,
+    next: Timestamp,
+    count: u32,
+}
+
+/// Synthesizes repeated presses for buttons the kernel doesn't autorepeat,
+/// such as the D-Pad.  Hats are repeated by turning them into D-Pad buttons
+/// first, with [`HatDpad`](crate::digital::HatDpad).
+///
+/// Events are passed in with [`Repeater::feed`], and due repeats are taken
+/// out with [`Repeater::poll`] as [`RepeatEvent`]s.
+/// [`Repeater::next_deadline`] tells when to poll next.
+#[derive(Debug)]
+pub struct Repeater<C = SystemClock> {
+    clock: C,
+    configs: HashMap<Button, RepeatConfig>,
+    held: Vec<(Button, Held)>,
+}
+
+impl Repeater {
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+}
+
+impl Default for Repeater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> Repeater<C> where C: Clock {
+    pub fn with_clock(clock: C) -> Self {
+        Self { clock, configs: HashMap::new(), held: Vec::new() }
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Enable repeat for `button`.
+    pub fn set(&mut self, button: Button, config: RepeatConfig) {
+        self.configs.insert(button, config);
+    }
+
+    /// Disable repeat for `button`.
+    pub fn remove(&mut self, button: Button) {
+        self.configs.remove(&button);
+        self.held.retain(|(held, _)| *held != button);
+    }
+
+    /// Track presses and releases of configured buttons.
+    pub fn feed<T>(&mut self, event: &Event<T>) where T: TryFrom<i32> {
+        let (Some(id), Some(pushed)) = (event.button(), event.pressed()) else {
+            if let Event::Disconnect = event {
+                self.held.clear();
+            }
+            return;
+        };
+        self.held.retain(|(held, _)| *held != id);
+        if !pushed {
+            return;
+        }
+        if let Some(config) = self.configs.get(&id) {
+            let next = self.clock.now() + config.delay;
+            let held = Held { config: *config, next, count: 0 };
+            self.held.push((id, held));
+        }
+    }
+
+    /// Next repeat that is due, if any.
+    pub fn poll(&mut self) -> Option<RepeatEvent> {
+        let now = self.clock.now();
+        let (button, held) = self.held
+            .iter_mut()
+            .filter(|(_, held)| held.next <= now)
+            .min_by_key(|(_, held)| held.next)?;
+        let period = held.config.period(held.count);
+        held.next = held.next + period;
+        if held.next <= now {
+            // Polled late, don't burst to catch up.
+            held.next = now + period;
+        }
+        held.count = held.count.saturating_add(1);
+        Some(RepeatEvent { button: *button, count: held.count })
+    }
+
+    /// When the next repeat is due, if any button is held.
+    pub fn next_deadline(&self) -> Option<Timestamp> {
+        self.held.iter().map(|(_, held)| held.next).min()
+    }
+
+    /// Forget all held buttons.
+    pub fn release_all(&mut self) {
+        self.held.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{at, ManualClock, MS};
+
+    fn repeater(config: RepeatConfig) -> Repeater<ManualClock> {
+        let mut repeater = Repeater::with_clock(ManualClock::new(at(0)));
+        repeater.set(Button::Up, config);
+        repeater
+    }
+
+    #[test]
+    fn initial_delay() {
+        let mut repeater = repeater(RepeatConfig::new(250 * MS, 50 * MS));
+        repeater.feed(&Event::<i32>::Button(Button::Up, true));
+        repeater.clock().advance(249 * MS);
+        assert_eq!(repeater.poll(), None);
+        repeater.clock().advance(MS);
+        assert_eq!(repeater.poll(), Some(RepeatEvent { button: Button::Up, count: 1 }));
+        assert_eq!(repeater.poll(), None);
+    }
+
+    #[test]
+    fn repeats_are_counted_per_press() {
+        let mut repeater = repeater(RepeatConfig::new(10 * MS, 10 * MS));
+        repeater.set(Button::Down, RepeatConfig::new(15 * MS, 10 * MS));
+        repeater.feed(&Event::<i32>::Button(Button::Up, true));
+        repeater.feed(&Event::<i32>::Button(Button::Down, true));
+        let mut repeats = Vec::new();
+        for _ in 0..3 {
+            repeater.clock().advance(10 * MS);
+            repeats.extend(std::iter::from_fn(|| repeater.poll()));
+        }
+        let expected = [(Button::Up, 1), (Button::Down, 1), (Button::Up, 2), (Button::Down, 2), (Button::Up, 3)];
+        assert_eq!(repeats, expected.map(|(button, count)| RepeatEvent { button, count }));
+
+        repeater.feed(&Event::<i32>::Button(Button::Up, false));
+        repeater.feed(&Event::<i32>::Button(Button::Up, true));
+        repeater.clock().advance(10 * MS);
+        assert_eq!(repeater.poll(), Some(RepeatEvent { button: Button::Down, count: 3 }));
+        assert_eq!(repeater.poll(), Some(RepeatEvent { button: Button::Up, count: 1 }));
+    }
+
+    #[test]
+    fn steady_rate() {
+        let mut repeater = repeater(RepeatConfig::new(250 * MS, 50 * MS));
+        repeater.feed(&Event::<i32>::Button(Button::Up, true));
+        repeater.clock().advance(250 * MS);
+        let mut repeats = 0;
+        for _ in 0..10 {
+            assert_eq!(repeater.next_deadline(), Some(repeater.clock().now()));
+            while repeater.poll().is_some() {
+                repeats += 1;
+            }
+            repeater.clock().advance(50 * MS);
+        }
+        assert_eq!(repeats, 10);
+    }
+
+    #[test]
+    fn acceleration() {
+        let config = RepeatConfig::new(100 * MS, 80 * MS).accelerate(0.5, 20 * MS);
+        let mut repeater = repeater(config);
+        repeater.feed(&Event::<i32>::Button(Button::Up, true));
+        let start = repeater.clock().now();
+        let mut times = Vec::new();
+        while times.len() < 5 {
+            let deadline = repeater.next_deadline().unwrap();
+            repeater.clock().set(deadline);
+            assert!(repeater.poll().is_some());
+            times.push(deadline.duration_since(start));
+        }
+        let expected = [100, 180, 220, 240, 260].map(|ms| ms * MS);
+        assert_eq!(times, expected);
+    }
+
+    #[test]
+    fn release_cancels() {
+        let mut repeater = repeater(RepeatConfig::new(100 * MS, 50 * MS));
+        repeater.feed(&Event::<i32>::Button(Button::Up, true));
+        repeater.clock().advance(50 * MS);
+        repeater.feed(&Event::<i32>::Button(Button::Up, false));
+        assert_eq!(repeater.next_deadline(), None);
+        repeater.clock().advance(MS * 1000);
+        assert_eq!(repeater.poll(), None);
+    }
+
+    #[test]
+    fn degenerate_configs_terminate() {
+        let configs = [
+            RepeatConfig::new(Duration::ZERO, Duration::ZERO),
+            RepeatConfig::new(MS, MS).accelerate(-2.0, Duration::ZERO),
+            RepeatConfig::new(MS, MS).accelerate(f32::NAN, Duration::ZERO),
+            RepeatConfig::new(MS, 10 * MS).accelerate(1e9, Duration::ZERO),
+        ];
+        for config in configs {
+            let mut repeater = repeater(config);
+            repeater.feed(&Event::<i32>::Button(Button::Up, true));
+            for _ in 0..100 {
+                repeater.clock().advance(10 * MS);
+                let mut burst = 0;
+                while repeater.poll().is_some() {
+                    burst += 1;
+                    assert!(burst <= 1, "{config:?}");
+                }
+            }
+        }
+    }
+}
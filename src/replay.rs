@@ -0,0 +1,277 @@
+use std::io;
+use std::time::Duration;
+
+use input_linux_sys::{input_event, EV_SYN, SYN_REPORT};
+
+use crate::evemu::Recording;
+use crate::time::{Clock, SystemClock, Timestamp};
+use crate::uinput::VirtualDevice;
+
+/// Slowest playback speed, whatever the configuration.
+pub const MIN_SPEED: f64 = 0.01;
+
+/// Pause between the end of a looped recording and its start, so a
+/// recording spanning no time doesn't spin.
+pub const MIN_LOOP_GAP: Duration = Duration::from_millis(1);
+
+/// Where a [`Replayer`] writes events to.
+pub trait Sink {
+    fn write_event(&mut self, type_: u16, code: u16, value: i32) -> io::Result<()>;
+}
+
+impl Sink for VirtualDevice {
+    fn write_event(&mut self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        VirtualDevice::write_event(self, type_, code, value)
+    }
+}
+
+/// Plays a recording back through a matching uinput device, keeping the
+/// recorded timing between events.
+#[derive(Debug)]
+pub struct Replayer<S = VirtualDevice, C = SystemClock> {
+    device: S,
+    clock: C,
+    recording: Recording,
+    position: usize,
+    speed: f64,
+    looping: bool,
+}
+
+impl Replayer {
+    /// Create the virtual device described by the recording.
+    pub fn new(recording: Recording) -> io::Result<Self> {
+        let device = VirtualDevice::create(&recording.description)?;
+        Ok(Self::with_device(device, recording))
+    }
+}
+
+impl<S> Replayer<S> where S: Sink {
+    /// Play into an already created device.
+    pub fn with_device(device: S, recording: Recording) -> Self {
+        Self { device, clock: SystemClock, recording, position: 0, speed: 1.0, looping: false }
+    }
+}
+
+impl<S, C> Replayer<S, C> where S: Sink, C: Clock {
+    /// Time playback with `clock`.
+    pub fn with_clock<D>(self, clock: D) -> Replayer<S, D> where D: Clock {
+        let Self { device, recording, position, speed, looping, .. } = self;
+        Replayer { device, clock, recording, position, speed, looping }
+    }
+
+    /// Playback speed multiplier, `2.0` plays twice as fast.  Clamped to at
+    /// least [`MIN_SPEED`]; NaN plays at normal speed.
+    pub fn speed(mut self, speed: f64) -> Self {
+        self.speed = if speed.is_nan() { 1.0 } else { speed.max(MIN_SPEED) };
+        self
+    }
+
+    /// Start over from the beginning after the last event.
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn device(&self) -> &S {
+        &self.device
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Recorded time of the next event relative to the first one.
+    pub fn position(&self) -> Option<Duration> {
+        self.offset(self.position)
+    }
+
+    /// Length of the recording.
+    pub fn duration(&self) -> Duration {
+        self.recording.events.len().checked_sub(1).and_then(|last| self.offset(last)).unwrap_or_default()
+    }
+
+    /// Jump to the first frame starting at or after `offset` into the
+    /// recording.  Events before it are skipped, not replayed.
+    pub fn seek(&mut self, offset: Duration) {
+        let events = &self.recording.events;
+        self.position = (0..events.len())
+            .find(|&i| {
+                let frame_start = i == 0 || is_syn_report(&events[i - 1]);
+                frame_start && self.offset(i).is_some_and(|at| at >= offset)
+            })
+            .unwrap_or(events.len());
+    }
+
+    /// Write the next event.  Returns the scaled delay until the event after
+    /// it, or `None` once the recording has finished.
+    pub fn step(&mut self) -> io::Result<Option<Duration>> {
+        let Some(event) = self.recording.events.get(self.position) else {
+            return Ok(None);
+        };
+        self.device.write_event(event.type_, event.code, event.value)?;
+        let current = self.offset(self.position).unwrap_or_default();
+        self.position += 1;
+        if self.position == self.recording.events.len() && self.looping {
+            self.position = 0;
+            return Ok(Some(MIN_LOOP_GAP));
+        }
+        Ok(self.offset(self.position).map(|next| self.scale(next.saturating_sub(current))))
+    }
+
+    /// Play until the end of the recording, sleeping between events.  Never
+    /// returns while looping, unless writing fails.
+    pub fn play(&mut self) -> io::Result<()> {
+        let mut base = self.position().unwrap_or_default();
+        let mut epoch = self.clock.now();
+        while let Some(offset) = self.position() {
+            let due = epoch + self.scale(offset.saturating_sub(base));
+            let now = self.clock.now();
+            if due > now {
+                self.clock.sleep(due - now);
+            }
+            let before = self.position;
+            let Some(delay) = self.step()? else {
+                break;
+            };
+            if self.position <= before {
+                // Looped around
+                base = Duration::ZERO;
+                epoch = self.clock.now() + delay;
+            }
+        }
+        Ok(())
+    }
+
+    /// Events with an invalid time count as happening with the last valid
+    /// one before them, or the first valid one at the start.
+    fn offset(&self, index: usize) -> Option<Duration> {
+        let events = self.recording.events.get(..=index)?;
+        let first = self.recording.events.iter().find_map(Timestamp::of)?;
+        let time = events.iter().rev().find_map(Timestamp::of).unwrap_or(first);
+        Some(time.duration_since(first))
+    }
+
+    fn scale(&self, delay: Duration) -> Duration {
+        delay.div_f64(self.speed)
+    }
+}
+
+fn is_syn_report(event: &input_event) -> bool {
+    event.type_ == EV_SYN as u16 && event.code == SYN_REPORT as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evemu::DeviceDescription;
+    use crate::time::{ManualClock, MS};
+
+    /// Records what was written and when.
+    struct Fake<'a> {
+        clock: &'a ManualClock,
+        written: Vec<(Duration, u16, i32)>,
+        fail_after: Option<usize>,
+    }
+
+    impl Sink for Fake<'_> {
+        fn write_event(&mut self, _: u16, code: u16, value: i32) -> io::Result<()> {
+            if self.fail_after == Some(self.written.len()) {
+                return Err(io::Error::other("full"));
+            }
+            self.written.push((self.clock.now().as_duration(), code, value));
+            Ok(())
+        }
+    }
+
+    fn event(ms: u32, type_: i32, code: u16, value: i32) -> input_event {
+        let time = Timestamp::from_duration(Duration::from_secs(100) + ms * MS).to_timeval();
+        input_event { time, type_: type_ as u16, code, value }
+    }
+
+    /// Two frames of an `EV_ABS` axis, at 0ms and 100ms.
+    fn recording() -> Recording {
+        let syn = |ms| event(ms, EV_SYN, SYN_REPORT as u16, 0);
+        let abs = |ms, value| event(ms, 0x03, 0x00, value);
+        let events = vec![abs(0, 1), syn(0), abs(100, 2), syn(100)];
+        Recording { description: DeviceDescription::default(), events }
+    }
+
+    fn replayer(clock: &ManualClock, recording: Recording) -> Replayer<Fake<'_>, &ManualClock> {
+        let fake = Fake { clock, written: Vec::new(), fail_after: None };
+        Replayer::with_device(fake, recording).with_clock(clock)
+    }
+
+    #[test]
+    fn play_keeps_timing() {
+        let clock = ManualClock::default();
+        let mut replayer = replayer(&clock, recording()).speed(2.0);
+        replayer.play().unwrap();
+        let times: Vec<_> = replayer.device().written.iter().map(|(at, _, _)| *at).collect();
+        assert_eq!(times, [Duration::ZERO, Duration::ZERO, 50 * MS, 50 * MS]);
+        assert_eq!(replayer.position(), None);
+        assert_eq!(replayer.step().unwrap(), None);
+    }
+
+    #[test]
+    fn step_and_seek() {
+        let clock = ManualClock::default();
+        let mut replayer = replayer(&clock, recording());
+        assert_eq!(replayer.duration(), 100 * MS);
+        assert_eq!(replayer.step().unwrap(), Some(Duration::ZERO));
+        assert_eq!(replayer.step().unwrap(), Some(100 * MS));
+        replayer.seek(MS);
+        assert_eq!(replayer.position(), Some(100 * MS));
+        // Seeking lands on frame starts only.
+        replayer.seek(Duration::ZERO);
+        assert_eq!(replayer.position(), Some(Duration::ZERO));
+        replayer.seek(Duration::from_secs(1));
+        assert_eq!(replayer.position(), None);
+        let values: Vec<_> = replayer.device().written.iter().map(|(_, _, value)| *value).collect();
+        assert_eq!(values, [1, 0]);
+    }
+
+    #[test]
+    fn speed_is_clamped() {
+        let clock = ManualClock::default();
+        for (speed, delay) in [(0.0, 10_000 * MS), (-1.0, 10_000 * MS), (f64::NAN, 100 * MS), (4.0, 25 * MS)] {
+            let mut replayer = replayer(&clock, recording()).speed(speed);
+            replayer.step().unwrap();
+            assert_eq!(replayer.step().unwrap(), Some(delay), "{speed}");
+        }
+    }
+
+    #[test]
+    fn looping_restarts_after_a_gap() {
+        let clock = ManualClock::default();
+        let single = Recording { description: DeviceDescription::default(), events: vec![event(0, 0x03, 0x00, 7)] };
+        let mut replayer = replayer(&clock, single).looping(true);
+        replayer.device.fail_after = Some(3);
+        assert!(replayer.play().is_err());
+        let times: Vec<_> = replayer.device().written.iter().map(|(at, _, _)| *at).collect();
+        assert_eq!(times, [Duration::ZERO, MIN_LOOP_GAP, 2 * MIN_LOOP_GAP]);
+    }
+
+    #[test]
+    fn looping_keeps_timing() {
+        let clock = ManualClock::default();
+        let mut replayer = replayer(&clock, recording()).looping(true);
+        replayer.device.fail_after = Some(6);
+        assert!(replayer.play().is_err());
+        let times: Vec<_> = replayer.device().written.iter().map(|(at, _, _)| *at).collect();
+        let second = 100 * MS + MIN_LOOP_GAP;
+        assert_eq!(times, [Duration::ZERO, Duration::ZERO, 100 * MS, 100 * MS, second, second]);
+    }
+
+    #[test]
+    fn invalid_times_take_the_previous_one() {
+        let clock = ManualClock::default();
+        let mut recording = recording();
+        recording.events[2].time.tv_sec = -1;
+        let mut replayer = replayer(&clock, recording);
+        assert_eq!(replayer.duration(), 100 * MS);
+        assert_eq!(replayer.step().unwrap(), Some(Duration::ZERO));
+        assert_eq!(replayer.step().unwrap(), Some(Duration::ZERO));
+        assert_eq!(replayer.step().unwrap(), Some(100 * MS));
+        assert_eq!(replayer.step().unwrap(), None);
+    }
+}
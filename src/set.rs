@@ -0,0 +1,298 @@
+//! Reading many devices at once through one epoll instance.
+
+use std::collections::VecDeque;
+use std::io;
+use std::mem::zeroed;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+use std::time::Duration;
+
+use input_linux_sys::input_event;
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::sys::epoll::{epoll_create1, epoll_ctl, epoll_wait, EpollCreateFlags, EpollEvent, EpollFlags, EpollOp};
+
+use crate::device::is_disconnect;
+use crate::{Device, Event};
+
+/// Handle of a device in a [`DeviceSet`].  Handles are never reused, so a
+/// stale one can't refer to a device added later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceId(pub u64);
+
+/// Raw events read from one device per wakeup.  Every ready device gets one
+/// read of at most this many events before any device gets a second one.
+const BATCH: usize = 64;
+
+/// Multiplexes several [`Device`]s, e.g. the stick, throttle and pedals of a
+/// HOTAS setup, into one stream of `(DeviceId, Event)` pairs.
+///
+/// Devices are switched to non-blocking mode while they are in the set.
+/// Each keeps its own hat state.  A device that goes away yields
+/// [`Event::Disconnect`] and is removed from the set.
+#[derive(Debug)]
+pub struct DeviceSet<T = i32> where T: TryFrom<i32> {
+    epoll: RawFd,
+    devices: Vec<(DeviceId, Device)>,
+    next_id: u64,
+    /// Where the next round of reads starts, rotated so no device is always
+    /// read first.
+    start: usize,
+    queue: VecDeque<(DeviceId, Event<T>)>,
+}
+
+impl<T> DeviceSet<T> where T: TryFrom<i32> {
+    pub fn new() -> io::Result<Self> {
+        let epoll = epoll_create1(EpollCreateFlags::EPOLL_CLOEXEC)?;
+        Ok(Self { epoll, devices: Vec::new(), next_id: 0, start: 0, queue: VecDeque::new() })
+    }
+
+    /// Add a device, returning its handle.
+    pub fn add(&mut self, device: Device) -> io::Result<DeviceId> {
+        let id = DeviceId(self.next_id);
+        set_nonblocking(&device, true)?;
+        let mut event = EpollEvent::new(EpollFlags::EPOLLIN, id.0);
+        epoll_ctl(self.epoll, EpollOp::EpollCtlAdd, device.as_raw_fd(), &mut event)?;
+        self.next_id += 1;
+        self.devices.push((id, device));
+        Ok(id)
+    }
+
+    /// Open a device node and add it.
+    pub fn open(&mut self, path: impl AsRef<Path>) -> io::Result<DeviceId> {
+        self.add(Device::open(path)?)
+    }
+
+    /// Take a device out of the set, dropping its queued events.  The device
+    /// is switched back to blocking mode.
+    pub fn remove(&mut self, id: DeviceId) -> Option<Device> {
+        let index = self.index(id)?;
+        let (_, device) = self.devices.remove(index);
+        let _ = epoll_ctl(self.epoll, EpollOp::EpollCtlDel, device.as_raw_fd(), None);
+        let _ = set_nonblocking(&device, false);
+        self.queue.retain(|(queued, _)| *queued != id);
+        Some(device)
+    }
+
+    pub fn get(&self, id: DeviceId) -> Option<&Device> {
+        self.index(id).map(|index| &self.devices[index].1)
+    }
+
+    pub fn get_mut(&mut self, id: DeviceId) -> Option<&mut Device> {
+        self.index(id).map(|index| &mut self.devices[index].1)
+    }
+
+    /// Handles of the devices in the set, in the order they were added.
+    pub fn ids(&self) -> impl Iterator<Item = DeviceId> + '_ {
+        self.devices.iter().map(|(id, _)| *id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Block until any device has an event.
+    pub fn next_event(&mut self) -> io::Result<(DeviceId, Event<T>)> {
+        loop {
+            if let Some(event) = self.poll(None)? {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Wait up to `timeout` (forever with `None`) for any device to have an
+    /// event.
+    pub fn poll(&mut self, timeout: Option<Duration>) -> io::Result<Option<(DeviceId, Event<T>)>> {
+        if let Some(event) = self.queue.pop_front() {
+            return Ok(Some(event));
+        }
+        let timeout = timeout.map_or(-1, timeout_ms);
+        let mut ready = vec![EpollEvent::empty(); self.devices.len().max(1)];
+        let count = match epoll_wait(self.epoll, &mut ready, timeout) {
+            Ok(count) => count,
+            Err(nix::errno::Errno::EINTR) => 0,
+            Err(err) => return Err(err.into()),
+        };
+        let mut ready: Vec<_> = ready[..count].iter().map(|event| DeviceId(event.data())).collect();
+        // Round robin over the ready devices in the order they were added.
+        ready.sort();
+        let start = self.start % ready.len().max(1);
+        ready.rotate_left(start);
+        self.start = self.start.wrapping_add(1);
+        for id in ready {
+            self.read(id)?;
+        }
+        Ok(self.queue.pop_front())
+    }
+
+    fn read(&mut self, id: DeviceId) -> io::Result<()> {
+        let Some(index) = self.index(id) else {
+            return Ok(());
+        };
+        // Safety: input_event is plain old data.
+        let mut buf: [input_event; BATCH] = unsafe { zeroed() };
+        let device = &mut self.devices[index].1;
+        match device.read_raw(&mut buf) {
+            Ok(count) => {
+                for raw in &buf[..count] {
+                    if let Some(event) = device.decode(raw) {
+                        self.queue.push_back((id, event));
+                    }
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            Err(err) if is_disconnect(&err) => {
+                self.remove(id);
+                self.queue.push_back((id, Event::Disconnect));
+            }
+            Err(err) => return Err(err),
+        }
+        Ok(())
+    }
+
+    fn index(&self, id: DeviceId) -> Option<usize> {
+        self.devices.binary_search_by_key(&id, |(id, _)| *id).ok()
+    }
+}
+
+impl<T> Iterator for DeviceSet<T> where T: TryFrom<i32> {
+    type Item = io::Result<(DeviceId, Event<T>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_event())
+    }
+}
+
+impl<T> AsRawFd for DeviceSet<T> where T: TryFrom<i32> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll
+    }
+}
+
+impl<T> Drop for DeviceSet<T> where T: TryFrom<i32> {
+    fn drop(&mut self) {
+        let _ = nix::unistd::close(self.epoll);
+    }
+}
+
+/// Timeout for `epoll_wait`, rounded up so a sub-millisecond timeout waits
+/// rather than busy-polls.
+fn timeout_ms(timeout: Duration) -> isize {
+    timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as isize
+}
+
+fn set_nonblocking(device: &Device, nonblocking: bool) -> io::Result<()> {
+    let flags = OFlag::from_bits_truncate(fcntl(device.as_raw_fd(), FcntlArg::F_GETFL)?);
+    let flags = if nonblocking { flags | OFlag::O_NONBLOCK } else { flags - OFlag::O_NONBLOCK };
+    fcntl(device.as_raw_fd(), FcntlArg::F_SETFL(flags))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::os::unix::io::FromRawFd;
+
+    use input_linux_sys::{ABS_HAT0X, ABS_HAT0Y, ABS_X, EV_ABS};
+
+    use super::*;
+    use crate::device::write_event;
+    use crate::{Axis, Direction, Hat};
+
+    /// A device reading from a pipe, and the writing end.
+    fn pipe() -> (Device, File) {
+        let (read, write) = nix::unistd::pipe().unwrap();
+        // Safety: both ends are fresh and owned by nothing else.
+        unsafe { (Device::from_file(File::from_raw_fd(read)), File::from_raw_fd(write)) }
+    }
+
+    fn abs(file: &File, code: i32, value: i32) {
+        write_event(file, EV_ABS as u16, code as u16, value).unwrap();
+    }
+
+    /// Everything queued right now.
+    fn drain(set: &mut DeviceSet) -> Vec<(DeviceId, Event)> {
+        std::iter::from_fn(|| set.poll(Some(Duration::ZERO)).unwrap()).collect()
+    }
+
+    #[test]
+    fn round_robin() {
+        let mut set = DeviceSet::new().unwrap();
+        let (device, a_file) = pipe();
+        let a = set.add(device).unwrap();
+        let (device, b_file) = pipe();
+        let b = set.add(device).unwrap();
+        for value in 0..100 {
+            abs(&a_file, ABS_X, value);
+            abs(&b_file, ABS_X, -value);
+        }
+
+        // A batch from each ready device per round, the first device of a
+        // round rotating so neither is always read first.
+        let ids: Vec<_> = drain(&mut set).into_iter().map(|(id, _)| id).collect();
+        let expected: Vec<_> = [(a, BATCH), (b, BATCH), (b, 100 - BATCH), (a, 100 - BATCH)]
+            .into_iter()
+            .flat_map(|(id, count)| std::iter::repeat_n(id, count))
+            .collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn flooding_device_doesnt_starve_others() {
+        let mut set = DeviceSet::new().unwrap();
+        let (device, a_file) = pipe();
+        let a = set.add(device).unwrap();
+        let (device, b_file) = pipe();
+        let b = set.add(device).unwrap();
+        for value in 0..1000 {
+            abs(&a_file, ABS_X, value);
+        }
+        abs(&b_file, ABS_X, 7);
+
+        let events = drain(&mut set);
+        let position = events.iter().position(|&(id, _)| id == b).unwrap();
+        assert!(position <= BATCH, "{position}");
+        assert_eq!(events[position].1, Event::Axis(Axis::JoyX, 7));
+        assert_eq!(events.iter().filter(|&&(id, _)| id == a).count(), 1000);
+    }
+
+    #[test]
+    fn hat_state_per_device() {
+        let mut set = DeviceSet::new().unwrap();
+        let (device, a_file) = pipe();
+        let a = set.add(device).unwrap();
+        let (device, b_file) = pipe();
+        let b = set.add(device).unwrap();
+
+        abs(&a_file, ABS_HAT0X, 1);
+        assert_eq!(drain(&mut set), [(a, Event::Hat(Hat::Pov, Direction::Right))]);
+        abs(&b_file, ABS_HAT0Y, -1);
+        assert_eq!(drain(&mut set), [(b, Event::Hat(Hat::Pov, Direction::Up))]);
+        abs(&a_file, ABS_HAT0Y, 1);
+        assert_eq!(drain(&mut set), [(a, Event::Hat(Hat::Pov, Direction::DownRight))]);
+    }
+
+    #[test]
+    fn disconnect_removes_device() {
+        let mut set = DeviceSet::<i32>::new().unwrap();
+        let (device, file) = pipe();
+        let id = set.add(device).unwrap();
+        drop(file);
+        assert_eq!(drain(&mut set), [(id, Event::Disconnect)]);
+        assert!(set.is_empty());
+        assert!(set.get(id).is_none());
+    }
+
+    #[test]
+    fn timeouts_round_up() {
+        assert_eq!(timeout_ms(Duration::ZERO), 0);
+        assert_eq!(timeout_ms(Duration::from_nanos(1)), 1);
+        assert_eq!(timeout_ms(Duration::from_micros(1500)), 2);
+        assert_eq!(timeout_ms(Duration::from_millis(3)), 3);
+        assert_eq!(timeout_ms(Duration::MAX), i32::MAX as isize);
+    }
+}
@@ -0,0 +1,195 @@
+//! Three-position HOTAS switches, reported as one position instead of a
+//! pair of buttons.
+
+use crate::{Button, Event};
+
+/// The three-way switches, each reported by a pair of buttons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Switch {
+    /// `PinkyForward` / `PinkyBackward`
+    Pinky,
+    /// `FlapsUp` / `FlapsDown`
+    Flaps,
+    /// `BoatForward` / `BoatBackward`
+    Boat,
+    /// `AutopilotPath` / `AutopilotAlt`
+    Autopilot,
+    /// `SpeedbrakeForward` / `SpeedbrakeBackward`
+    Speedbrake,
+    /// `ChinaForward` / `ChinaBackward`
+    China,
+    /// `EngineIgnitionL` / `EngineMotorL`
+    EngineL,
+    /// `EngineIgnitionR` / `EngineMotorR`
+    EngineR,
+}
+
+/// Position of a [`Switch`].  `Neutral` is the middle position, with
+/// neither button on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThreeWay {
+    Forward,
+    #[default]
+    Neutral,
+    Backward,
+}
+
+impl Switch {
+    /// Every switch, in declaration order.
+    pub const ALL: [Switch; 8] = [
+        Switch::Pinky, Switch::Flaps, Switch::Boat, Switch::Autopilot,
+        Switch::Speedbrake, Switch::China, Switch::EngineL, Switch::EngineR,
+    ];
+
+    /// The switch of a button event, whether it is the forward position and
+    /// whether that position is on.
+    pub fn of<T>(event: &Event<T>) -> Option<(Switch, bool, bool)> where T: TryFrom<i32> {
+        Some(match *event {
+            Event::Button(Button::PinkyForward, on) => (Switch::Pinky, true, on),
+            Event::Button(Button::PinkyBackward, on) => (Switch::Pinky, false, on),
+            Event::Button(Button::FlapsUp, on) => (Switch::Flaps, true, on),
+            Event::Button(Button::FlapsDown, on) => (Switch::Flaps, false, on),
+            Event::Button(Button::BoatForward, on) => (Switch::Boat, true, on),
+            Event::Button(Button::BoatBackward, on) => (Switch::Boat, false, on),
+            Event::Button(Button::AutopilotPath, on) => (Switch::Autopilot, true, on),
+            Event::Button(Button::AutopilotAlt, on) => (Switch::Autopilot, false, on),
+            Event::Button(Button::SpeedbrakeForward, on) => (Switch::Speedbrake, true, on),
+            Event::Button(Button::SpeedbrakeBackward, on) => (Switch::Speedbrake, false, on),
+            Event::Button(Button::ChinaForward, on) => (Switch::China, true, on),
+            Event::Button(Button::ChinaBackward, on) => (Switch::China, false, on),
+            Event::Button(Button::EngineIgnitionL, on) => (Switch::EngineL, true, on),
+            Event::Button(Button::EngineMotorL, on) => (Switch::EngineL, false, on),
+            Event::Button(Button::EngineIgnitionR, on) => (Switch::EngineR, true, on),
+            Event::Button(Button::EngineMotorR, on) => (Switch::EngineR, false, on),
+            _ => return None,
+        })
+    }
+
+    /// The forward and backward button events of a position.
+    pub fn events<T>(self, position: ThreeWay) -> [Event<T>; 2] where T: TryFrom<i32> {
+        let [forward, backward] = match self {
+            Switch::Pinky => [Button::PinkyForward, Button::PinkyBackward],
+            Switch::Flaps => [Button::FlapsUp, Button::FlapsDown],
+            Switch::Boat => [Button::BoatForward, Button::BoatBackward],
+            Switch::Autopilot => [Button::AutopilotPath, Button::AutopilotAlt],
+            Switch::Speedbrake => [Button::SpeedbrakeForward, Button::SpeedbrakeBackward],
+            Switch::China => [Button::ChinaForward, Button::ChinaBackward],
+            Switch::EngineL => [Button::EngineIgnitionL, Button::EngineMotorL],
+            Switch::EngineR => [Button::EngineIgnitionR, Button::EngineMotorR],
+        };
+        [
+            Event::Button(forward, position == ThreeWay::Forward),
+            Event::Button(backward, position == ThreeWay::Backward),
+        ]
+    }
+}
+
+/// What a [`SwitchTracker`] emits: the events it was fed, with the switch
+/// button events replaced by switch positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SwitchOutput<T = i32> where T: TryFrom<i32> {
+    Event(Event<T>),
+    Switch(Switch, ThreeWay),
+}
+
+/// Merges the two buttons of each [`Switch`] into [`ThreeWay`]
+/// positions.
+///
+/// A switch moved straight from one end to the other may report the new
+/// position before releasing the old one.  The latest position switched on
+/// wins, and the late release is dropped, so both ends are never reported
+/// on together.  `Neutral` is reported once neither end is on.
+#[derive(Debug, Clone, Default)]
+pub struct SwitchTracker {
+    /// Forward and backward positions that are on, per switch.
+    on: [(bool, bool); 8],
+    reported: [ThreeWay; 8],
+}
+
+impl SwitchTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn position(&self, switch: Switch) -> ThreeWay {
+        self.reported[switch as usize]
+    }
+
+    /// Handle one event.  Switch events that don't change the position give
+    /// nothing.
+    pub fn feed<T>(&mut self, event: Event<T>) -> Option<SwitchOutput<T>> where T: TryFrom<i32> {
+        let Some((switch, forward, on)) = Switch::of(&event) else {
+            return Some(SwitchOutput::Event(event));
+        };
+        let state = &mut self.on[switch as usize];
+        match (forward, on) {
+            (true, true) => *state = (true, false),
+            (false, true) => *state = (false, true),
+            (true, false) => state.0 = false,
+            (false, false) => state.1 = false,
+        }
+        let position = match *state {
+            (true, _) => ThreeWay::Forward,
+            (_, true) => ThreeWay::Backward,
+            _ => ThreeWay::Neutral,
+        };
+        let reported = &mut self.reported[switch as usize];
+        if *reported == position {
+            return None;
+        }
+        *reported = position;
+        Some(SwitchOutput::Switch(switch, position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pinky(forward: bool, on: bool) -> Event {
+        Event::Button(if forward { Button::PinkyForward } else { Button::PinkyBackward }, on)
+    }
+
+    #[test]
+    fn straight_to_the_other_end() {
+        let mut tracker = SwitchTracker::new();
+        assert_eq!(tracker.feed(pinky(true, true)), Some(SwitchOutput::Switch(Switch::Pinky, ThreeWay::Forward)));
+        assert_eq!(tracker.feed(pinky(false, true)), Some(SwitchOutput::Switch(Switch::Pinky, ThreeWay::Backward)));
+        assert_eq!(tracker.feed(pinky(true, false)), None);
+        assert_eq!(tracker.position(Switch::Pinky), ThreeWay::Backward);
+    }
+
+    #[test]
+    fn neutral_once_both_released() {
+        let mut tracker = SwitchTracker::new();
+        tracker.feed(pinky(true, true));
+        tracker.feed(pinky(false, true));
+        assert_eq!(tracker.feed(pinky(true, false)), None);
+        assert_eq!(tracker.feed(pinky(false, false)), Some(SwitchOutput::Switch(Switch::Pinky, ThreeWay::Neutral)));
+        assert_eq!(tracker.feed(pinky(false, false)), None);
+        assert_eq!(tracker.position(Switch::Pinky), ThreeWay::Neutral);
+    }
+
+    #[test]
+    fn other_events_pass_through() {
+        let mut tracker = SwitchTracker::new();
+        for event in [Event::Button(Button::ActionA, true), Event::Axis(crate::Axis::JoyX, 5), Event::Disconnect] {
+            assert_eq!(tracker.feed(event), Some(SwitchOutput::Event(event)));
+        }
+        assert!(Switch::ALL.iter().all(|&switch| tracker.position(switch) == ThreeWay::Neutral));
+    }
+
+    #[test]
+    fn events_round_trip() {
+        for switch in Switch::ALL {
+            for position in [ThreeWay::Forward, ThreeWay::Neutral, ThreeWay::Backward] {
+                let [forward, backward] = switch.events::<i32>(position);
+                assert_eq!(Switch::of(&forward), Some((switch, true, position == ThreeWay::Forward)));
+                assert_eq!(Switch::of(&backward), Some((switch, false, position == ThreeWay::Backward)));
+            }
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! Graphics tablets and styluses.
+//!
+//! [`crate::Event`] is a gamepad mapping: it reads `ABS_WHEEL` as a
+//! steering wheel and has no notion of tools.  [`TabletDecoder`] decodes the
+//! raw events of a tablet instead, mapping the active area onto an output
+//! rectangle and shaping the pressure with a [`ResponseCurve`].
+
+use std::collections::{BTreeMap, VecDeque};
+use std::f32::consts::PI;
+use std::io;
+use std::path::Path;
+
+use input_linux_sys::*;
+
+use crate::axis::ResponseCurve;
+use crate::device::is_disconnect;
+use crate::{AbsInfo, Device};
+
+/// The tool in proximity, from the `BTN_TOOL_*` codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tool {
+    Pen,
+    /// The eraser end of a pen.
+    Rubber,
+    Brush,
+    Pencil,
+    Airbrush,
+    Finger,
+    Mouse,
+    Lens,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 8] = [
+        Tool::Pen, Tool::Rubber, Tool::Brush, Tool::Pencil,
+        Tool::Airbrush, Tool::Finger, Tool::Mouse, Tool::Lens,
+    ];
+
+    pub fn from_code(code: u16) -> Option<Tool> {
+        let index = (code as i32).checked_sub(BTN_TOOL_PEN)?;
+        Tool::ALL.get(usize::try_from(index).ok()?).copied()
+    }
+
+    /// `BTN_TOOL_*` code.
+    pub fn code(self) -> u16 {
+        (BTN_TOOL_PEN + self as i32) as u16
+    }
+}
+
+/// The buttons on the barrel of a stylus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StylusButton {
+    /// `BTN_STYLUS`, usually the lower one.
+    Primary,
+    /// `BTN_STYLUS2`
+    Secondary,
+    /// `BTN_STYLUS3`
+    Tertiary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TabletEvent {
+    /// A tool came into (`true`) or left (`false`) proximity.
+    Proximity(Tool, bool),
+    /// The tip touches the surface.
+    Touch(bool),
+    /// Position on the output rectangle.
+    Position { x: f32, y: f32 },
+    /// Pressure after the curve, `0.0..=1.0`.
+    Pressure(f32),
+    /// Hover distance as a fraction of its range, `0.0..=1.0`
+    /// (`ABS_DISTANCE`).
+    Distance(f32),
+    /// Tilt in degrees, positive to the right and towards the user.
+    Tilt { x: f32, y: f32 },
+    /// Airbrush finger wheel or lens cursor wheel as a fraction of its
+    /// range, `0.0..=1.0` (`ABS_WHEEL`).
+    Wheel(f32),
+    Button(StylusButton, bool),
+    /// Any other key, e.g. the express keys of a pad, by `EV_KEY` code.
+    Key(u16, bool),
+    Disconnect,
+}
+
+/// An axis-aligned rectangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Rect {
+    /// `0.0..=1.0` on both axes.
+    pub const UNIT: Rect = Rect { left: 0.0, top: 0.0, right: 1.0, bottom: 1.0 };
+
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self { left, top, right, bottom }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+
+    /// The largest rectangle centered in this one with the aspect ratio of
+    /// `other`, e.g. the part of a tablet that maps onto a screen without
+    /// distortion.
+    pub fn fit(&self, other: &Rect) -> Rect {
+        let aspect = other.width() / other.height();
+        if self.width() / self.height() > aspect {
+            let margin = (self.width() - self.height() * aspect) / 2.0;
+            Rect { left: self.left + margin, right: self.right - margin, ..*self }
+        } else {
+            let margin = (self.height() - self.width() / aspect) / 2.0;
+            Rect { top: self.top + margin, bottom: self.bottom - margin, ..*self }
+        }
+    }
+
+    /// Map a point of this rectangle onto `to`, clamping points outside.
+    pub fn map(&self, to: &Rect, x: f32, y: f32) -> (f32, f32) {
+        let fraction = |value: f32, from: f32, span: f32| {
+            if span.abs() <= f32::EPSILON { 0.0 } else { ((value - from) / span).clamp(0.0, 1.0) }
+        };
+        (
+            to.left + fraction(x, self.left, self.width()) * to.width(),
+            to.top + fraction(y, self.top, self.height()) * to.height(),
+        )
+    }
+}
+
+/// Settings of a [`TabletDecoder`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TabletConfig {
+    /// Part of the surface in use, in device units.  `None` for all of it.
+    pub area: Option<Rect>,
+    /// Where the area is mapped to, e.g. a screen in pixels.
+    pub output: Rect,
+    /// Maps the raw pressure, as a fraction of its range, to the reported
+    /// pressure.
+    pub pressure: ResponseCurve,
+}
+
+impl TabletConfig {
+    pub fn new() -> Self {
+        Self { area: None, output: Rect::UNIT, pressure: ResponseCurve::Linear }
+    }
+
+    pub fn area(mut self, area: Rect) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    pub fn output(mut self, output: Rect) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn pressure(mut self, curve: ResponseCurve) -> Self {
+        self.pressure = curve;
+        self
+    }
+}
+
+impl Default for TabletConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Decodes the raw events of a tablet a frame at a time.
+///
+/// Feed it every raw event; the events of a frame come out at its
+/// `SYN_REPORT`, with tools entering proximity first and leaving last, so
+/// the position of a frame always belongs to a tool in proximity.
+#[derive(Debug, Clone)]
+pub struct TabletDecoder {
+    config: TabletConfig,
+    axes: BTreeMap<u16, AbsInfo>,
+    position: (i32, i32),
+    tilt: (i32, i32),
+    moved: bool,
+    tilted: bool,
+    entering: Vec<TabletEvent>,
+    pending: Vec<TabletEvent>,
+    leaving: Vec<TabletEvent>,
+    dropped: bool,
+}
+
+impl TabletDecoder {
+    /// Decode a tablet with the given `ABS_*` axis ranges.
+    pub fn new(axes: impl IntoIterator<Item = (u16, AbsInfo)>, config: TabletConfig) -> Self {
+        let axes: BTreeMap<_, _> = axes.into_iter().collect();
+        let value = |code: i32| axes.get(&(code as u16)).map_or(0, |info| info.value);
+        Self {
+            position: (value(ABS_X), value(ABS_Y)),
+            tilt: (value(ABS_TILT_X), value(ABS_TILT_Y)),
+            config,
+            axes,
+            moved: false,
+            tilted: false,
+            entering: Vec::new(),
+            pending: Vec::new(),
+            leaving: Vec::new(),
+            dropped: false,
+        }
+    }
+
+    pub fn for_device(device: &Device, config: TabletConfig) -> io::Result<Self> {
+        let mut axes = Vec::new();
+        for code in [ABS_X, ABS_Y, ABS_PRESSURE, ABS_DISTANCE, ABS_TILT_X, ABS_TILT_Y, ABS_WHEEL] {
+            if device.has(EV_ABS as _, code as _)? {
+                axes.push((code as u16, device.absinfo(code as _)?));
+            }
+        }
+        Ok(Self::new(axes, config))
+    }
+
+    pub fn config(&self) -> &TabletConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: TabletConfig) {
+        self.config = config;
+    }
+
+    /// The whole surface, in device units.
+    pub fn surface(&self) -> Rect {
+        let range = |code: i32| self.axes.get(&(code as u16)).map_or((0.0, 0.0), |info| {
+            (info.minimum as f32, info.maximum as f32)
+        });
+        let ((left, right), (top, bottom)) = (range(ABS_X), range(ABS_Y));
+        Rect { left, top, right, bottom }
+    }
+
+    /// Handle one raw event, returning the events of the frame it ends.
+    pub fn feed(&mut self, raw: &input_event) -> Vec<TabletEvent> {
+        match (raw.type_ as i32, raw.code as i32) {
+            (EV_SYN, SYN_REPORT) if self.dropped => self.dropped = false,
+            (EV_SYN, SYN_REPORT) => return self.sync(),
+            (EV_SYN, SYN_DROPPED) => {
+                self.entering.clear();
+                self.pending.clear();
+                self.leaving.clear();
+                (self.moved, self.tilted) = (false, false);
+                self.dropped = true;
+            }
+            _ if self.dropped => {}
+            (EV_KEY, code) => self.key(code as u16, raw.value != 0),
+            (EV_ABS, code) => self.abs(code, raw.value),
+            _ => {}
+        }
+        Vec::new()
+    }
+
+    fn key(&mut self, code: u16, on: bool) {
+        let event = match code as i32 {
+            BTN_TOUCH => TabletEvent::Touch(on),
+            BTN_STYLUS => TabletEvent::Button(StylusButton::Primary, on),
+            BTN_STYLUS2 => TabletEvent::Button(StylusButton::Secondary, on),
+            BTN_STYLUS3 => TabletEvent::Button(StylusButton::Tertiary, on),
+            _ => match Tool::from_code(code) {
+                Some(tool) if on => {
+                    self.entering.push(TabletEvent::Proximity(tool, true));
+                    return;
+                }
+                Some(tool) => {
+                    self.leaving.push(TabletEvent::Proximity(tool, false));
+                    return;
+                }
+                None => TabletEvent::Key(code, on),
+            },
+        };
+        self.pending.push(event);
+    }
+
+    fn abs(&mut self, code: i32, value: i32) {
+        match code {
+            ABS_X => (self.position.0, self.moved) = (value, true),
+            ABS_Y => (self.position.1, self.moved) = (value, true),
+            ABS_TILT_X => (self.tilt.0, self.tilted) = (value, true),
+            ABS_TILT_Y => (self.tilt.1, self.tilted) = (value, true),
+            ABS_PRESSURE => {
+                let pressure = self.config.pressure.apply(self.fraction(code, value));
+                self.pending.push(TabletEvent::Pressure(pressure));
+            }
+            ABS_DISTANCE => self.pending.push(TabletEvent::Distance(self.fraction(code, value))),
+            ABS_WHEEL => self.pending.push(TabletEvent::Wheel(self.fraction(code, value))),
+            _ => {}
+        }
+    }
+
+    /// Position of a value within the range of its axis, `0.0` for axes
+    /// with unknown range.
+    fn fraction(&self, code: i32, value: i32) -> f32 {
+        self.axes.get(&(code as u16)).map_or(0.0, |info| {
+            let span = (info.maximum as f32 - info.minimum as f32).max(1.0);
+            ((value as f32 - info.minimum as f32) / span).clamp(0.0, 1.0)
+        })
+    }
+
+    fn sync(&mut self) -> Vec<TabletEvent> {
+        let mut frame = std::mem::take(&mut self.entering);
+        if std::mem::take(&mut self.moved) {
+            let area = self.config.area.unwrap_or_else(|| self.surface());
+            let (x, y) = area.map(&self.config.output, self.position.0 as f32, self.position.1 as f32);
+            frame.push(TabletEvent::Position { x, y });
+        }
+        if std::mem::take(&mut self.tilted) {
+            frame.push(TabletEvent::Tilt { x: self.degrees(ABS_TILT_X, self.tilt.0), y: self.degrees(ABS_TILT_Y, self.tilt.1) });
+        }
+        frame.append(&mut self.pending);
+        frame.append(&mut self.leaving);
+        frame
+    }
+
+    /// Tilt axes have their resolution in units per radian.  Devices that
+    /// don't set it report degrees.
+    fn degrees(&self, code: i32, value: i32) -> f32 {
+        match self.axes.get(&(code as u16)) {
+            Some(info) if info.resolution > 0 => value as f32 / info.resolution as f32 * 180.0 / PI,
+            _ => value as f32,
+        }
+    }
+}
+
+/// A tablet device, read through a [`TabletDecoder`].
+#[derive(Debug)]
+pub struct Tablet {
+    device: Device,
+    decoder: TabletDecoder,
+    queue: VecDeque<TabletEvent>,
+    disconnected: bool,
+}
+
+impl Tablet {
+    pub fn open(path: impl AsRef<Path>, config: TabletConfig) -> io::Result<Self> {
+        Self::new(Device::open(path)?, config)
+    }
+
+    pub fn new(device: Device, config: TabletConfig) -> io::Result<Self> {
+        let decoder = TabletDecoder::for_device(&device, config)?;
+        Ok(Self { device, decoder, queue: VecDeque::new(), disconnected: false })
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn decoder(&self) -> &TabletDecoder {
+        &self.decoder
+    }
+
+    pub fn decoder_mut(&mut self) -> &mut TabletDecoder {
+        &mut self.decoder
+    }
+
+    /// Block until the next event.  Once the device goes away this returns
+    /// [`TabletEvent::Disconnect`] a single time, and errors afterwards.
+    pub fn next_event(&mut self) -> io::Result<TabletEvent> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Ok(event);
+            }
+            if self.disconnected {
+                return Err(io::Error::from_raw_os_error(nix::libc::ENODEV));
+            }
+            match self.device.read_event() {
+                Ok(raw) => self.queue.extend(self.decoder.feed(&raw)),
+                Err(err) if is_disconnect(&err) => {
+                    self.disconnected = true;
+                    return Ok(TabletEvent::Disconnect);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decoder(config: TabletConfig) -> TabletDecoder {
+        let info = |maximum, resolution| AbsInfo { minimum: 0, maximum, resolution, ..AbsInfo::default() };
+        let tilt = AbsInfo { minimum: -64, maximum: 63, resolution: 57, ..AbsInfo::default() };
+        let axes = [
+            (ABS_X as u16, info(2000, 100)),
+            (ABS_Y as u16, info(1000, 100)),
+            (ABS_PRESSURE as u16, info(1000, 0)),
+            (ABS_DISTANCE as u16, info(64, 0)),
+            (ABS_TILT_X as u16, tilt),
+            (ABS_TILT_Y as u16, AbsInfo { resolution: 0, ..tilt }),
+            (ABS_WHEEL as u16, info(1023, 0)),
+        ];
+        TabletDecoder::new(axes, config)
+    }
+
+    fn frame(decoder: &mut TabletDecoder, events: &[(i32, i32, i32)]) -> Vec<TabletEvent> {
+        let raw = |(type_, code, value): (i32, i32, i32)| input_event {
+            time: timeval { tv_sec: 0, tv_usec: 0 },
+            type_: type_ as u16,
+            code: code as u16,
+            value,
+        };
+        for &event in events {
+            assert!(decoder.feed(&raw(event)).is_empty());
+        }
+        decoder.feed(&raw((EV_SYN, SYN_REPORT, 0)))
+    }
+
+    #[test]
+    fn rect_fit() {
+        let tablet = Rect::new(0.0, 0.0, 2000.0, 1000.0);
+        assert_eq!(tablet.fit(&Rect::new(0.0, 0.0, 1000.0, 1000.0)), Rect::new(500.0, 0.0, 1500.0, 1000.0));
+        assert_eq!(tablet.fit(&Rect::new(0.0, 0.0, 4000.0, 1000.0)), Rect::new(0.0, 250.0, 2000.0, 750.0));
+    }
+
+    #[test]
+    fn rect_map() {
+        let from = Rect::new(100.0, 100.0, 300.0, 200.0);
+        let to = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        assert_eq!(from.map(&to, 200.0, 150.0), (960.0, 540.0));
+        assert_eq!(from.map(&to, 0.0, 500.0), (0.0, 1080.0));
+        let empty = Rect::new(5.0, 5.0, 5.0, 5.0);
+        assert_eq!(empty.map(&to, 5.0, 5.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn position_on_output() {
+        let config = TabletConfig::new().area(Rect::new(0.0, 0.0, 1000.0, 1000.0)).output(Rect::new(0.0, 0.0, 100.0, 50.0));
+        let mut decoder = decoder(config);
+        assert_eq!(frame(&mut decoder, &[(EV_ABS, ABS_X, 500), (EV_ABS, ABS_Y, 2000)]), [
+            TabletEvent::Position { x: 50.0, y: 50.0 },
+        ]);
+    }
+
+    #[test]
+    fn pressure_curve() {
+        let mut decoder = decoder(TabletConfig::new().pressure(ResponseCurve::Exponential(2.0)));
+        assert_eq!(frame(&mut decoder, &[(EV_ABS, ABS_PRESSURE, 500)]), [TabletEvent::Pressure(0.25)]);
+        assert_eq!(frame(&mut decoder, &[(EV_ABS, ABS_PRESSURE, 1000)]), [TabletEvent::Pressure(1.0)]);
+    }
+
+    #[test]
+    fn distance_and_wheel_are_scaled() {
+        let mut decoder = decoder(TabletConfig::new());
+        assert_eq!(frame(&mut decoder, &[(EV_ABS, ABS_DISTANCE, 16), (EV_ABS, ABS_WHEEL, 1023)]), [
+            TabletEvent::Distance(0.25),
+            TabletEvent::Wheel(1.0),
+        ]);
+    }
+
+    #[test]
+    fn proximity_order() {
+        let mut decoder = decoder(TabletConfig::new());
+        let events = frame(&mut decoder, &[
+            (EV_ABS, ABS_X, 0),
+            (EV_KEY, BTN_TOOL_PEN, 1),
+            (EV_KEY, BTN_TOUCH, 1),
+            (EV_KEY, BTN_TOOL_FINGER, 1),
+        ]);
+        assert_eq!(events, [
+            TabletEvent::Proximity(Tool::Pen, true),
+            TabletEvent::Proximity(Tool::Finger, true),
+            TabletEvent::Position { x: 0.0, y: 0.0 },
+            TabletEvent::Touch(true),
+        ]);
+        let events = frame(&mut decoder, &[
+            (EV_KEY, BTN_TOOL_PEN, 0),
+            (EV_KEY, BTN_TOUCH, 0),
+            (EV_KEY, BTN_TOOL_RUBBER, 1),
+            (EV_ABS, ABS_X, 2000),
+        ]);
+        assert_eq!(events, [
+            TabletEvent::Proximity(Tool::Rubber, true),
+            TabletEvent::Position { x: 1.0, y: 0.0 },
+            TabletEvent::Touch(false),
+            TabletEvent::Proximity(Tool::Pen, false),
+        ]);
+    }
+
+    #[test]
+    fn tilt_in_degrees() {
+        let mut decoder = decoder(TabletConfig::new());
+        let events = frame(&mut decoder, &[(EV_ABS, ABS_TILT_X, 57), (EV_ABS, ABS_TILT_Y, -30)]);
+        let [TabletEvent::Tilt { x, y }] = events[..] else {
+            panic!("{events:?}");
+        };
+        assert!((x - 180.0 / PI).abs() < 1e-3, "{x}");
+        assert_eq!(y, -30.0);
+    }
+}
@@ -0,0 +1,155 @@
+use std::cell::Cell;
+use std::ops::{Add, Sub};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use input_linux_sys::{input_event, timeval};
+
+/// Point in time as reported by evdev (`CLOCK_REALTIME` unless changed with
+/// `EVIOCSCLOCKID`), stored as the duration since the clock's epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timestamp(Duration);
+
+impl Timestamp {
+    pub const fn from_duration(since_epoch: Duration) -> Self {
+        Self(since_epoch)
+    }
+
+    /// `None` for times before the epoch and microseconds outside
+    /// `0..1_000_000`, which the kernel never reports but a recording
+    /// might.
+    pub fn from_timeval(time: &timeval) -> Option<Self> {
+        let secs = u64::try_from(time.tv_sec).ok()?;
+        let micros = u32::try_from(time.tv_usec).ok().filter(|&micros| micros < 1_000_000)?;
+        Some(Self(Duration::new(secs, micros * 1000)))
+    }
+
+    /// Timestamp of a raw event, see [`Timestamp::from_timeval`].
+    pub fn of(input_event: &input_event) -> Option<Self> {
+        Self::from_timeval(&input_event.time)
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+
+    pub fn to_timeval(&self) -> timeval {
+        timeval {
+            tv_sec: self.0.as_secs() as _,
+            tv_usec: self.0.subsec_micros() as _,
+        }
+    }
+
+    /// Time elapsed since `earlier`, or zero if `earlier` is in the future.
+    pub fn duration_since(&self, earlier: Timestamp) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, rhs: Duration) -> Timestamp {
+        Timestamp(self.0 + rhs)
+    }
+}
+
+impl Sub for Timestamp {
+    type Output = Duration;
+
+    fn sub(self, rhs: Timestamp) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+/// One millisecond, to write test durations with.
+#[cfg(test)]
+pub(crate) const MS: Duration = Duration::from_millis(1);
+
+/// `ms` milliseconds after an arbitrary start time, for tests.
+#[cfg(test)]
+pub(crate) fn at(ms: u64) -> Timestamp {
+    Timestamp(Duration::from_secs(1) + Duration::from_millis(ms))
+}
+
+/// Source of the current time for timer driven layers.
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+
+    /// Wait for `duration` to pass.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Wall clock, matching the default evdev event timestamps.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+}
+
+/// Clock that only moves when told to, for deterministic replay and tests.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Cell<Timestamp>,
+}
+
+impl ManualClock {
+    pub fn new(start: Timestamp) -> Self {
+        Self { now: Cell::new(start) }
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        self.now.get()
+    }
+
+    /// Advances the clock instead of waiting.
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeval(tv_sec: i64, tv_usec: i64) -> timeval {
+        timeval { tv_sec: tv_sec as _, tv_usec: tv_usec as _ }
+    }
+
+    #[test]
+    fn from_timeval_validates() {
+        let time = Timestamp::from_timeval(&timeval(12, 345_678)).unwrap();
+        assert_eq!(time.as_duration(), Duration::from_micros(12_345_678));
+        assert_eq!(Timestamp::from_timeval(&time.to_timeval()), Some(time));
+        assert!(Timestamp::from_timeval(&timeval(i64::MAX, 999_999)).is_some());
+
+        for (sec, usec) in [(-1, 0), (0, -1), (0, 1_000_000), (1, i64::MAX), (i64::MIN, 0)] {
+            assert_eq!(Timestamp::from_timeval(&timeval(sec, usec)), None, "{sec}.{usec}");
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
+
+use input_linux_sys::*;
+use nix::libc::c_char;
+
+use crate::device::{bit_is_set, check, code_count, c_string, write_event};
+use crate::evemu::DeviceDescription;
+
+/// A virtual input device created through `/dev/uinput`.  The device is
+/// destroyed when dropped.
+#[derive(Debug)]
+pub struct VirtualDevice {
+    file: File,
+}
+
+impl VirtualDevice {
+    /// Create a device matching a description.  Force feedback is not
+    /// supported, since nothing would service effect uploads.
+    pub fn create(description: &DeviceDescription) -> io::Result<Self> {
+        Self::create_at("/dev/uinput", description)
+    }
+
+    pub fn create_at(uinput: impl AsRef<Path>, description: &DeviceDescription) -> io::Result<Self> {
+        let file = OpenOptions::new().write(true).open(uinput)?;
+        let fd = file.as_raw_fd();
+
+        for type_ in 1..EV_CNT as u16 {
+            if type_ != EV_FF as u16 && description.has(0, type_) {
+                check(unsafe { ui_set_evbit(fd, type_ as _) })?;
+            }
+        }
+        for (&type_, mask) in &description.bits {
+            let set_bit = match type_ as _ {
+                EV_KEY => ui_set_keybit,
+                EV_REL => ui_set_relbit,
+                EV_ABS => ui_set_absbit,
+                EV_MSC => ui_set_mscbit,
+                EV_LED => ui_set_ledbit,
+                EV_SND => ui_set_sndbit,
+                EV_SW => ui_set_swbit,
+                _ => continue,
+            };
+            for code in 0..code_count(type_) as u16 {
+                if bit_is_set(mask, code) {
+                    check(unsafe { set_bit(fd, code as _) })?;
+                }
+            }
+        }
+        for prop in 0..INPUT_PROP_CNT as u16 {
+            if bit_is_set(&description.props, prop) {
+                check(unsafe { ui_set_propbit(fd, prop as _) })?;
+            }
+        }
+        for (&code, info) in &description.abs {
+            let setup = uinput_abs_setup { code, absinfo: (*info).into() };
+            check(unsafe { ui_abs_setup(fd, &setup) })?;
+        }
+
+        let id = description.id;
+        let mut setup = uinput_setup {
+            id: input_id { bustype: id.bustype, vendor: id.vendor, product: id.product, version: id.version },
+            name: [0; UINPUT_MAX_NAME_SIZE as usize],
+            ff_effects_max: 0,
+        };
+        // Leave room for the terminating nul.
+        for (dst, src) in setup.name.iter_mut().zip(description.name.bytes().take(UINPUT_MAX_NAME_SIZE as usize - 1)) {
+            *dst = src as c_char;
+        }
+        check(unsafe { ui_dev_setup(fd, &setup) })?;
+        check(unsafe { ui_dev_create(fd) })?;
+        Ok(Self { file })
+    }
+
+    /// Name of the created device in `/sys/devices/virtual/input/`.
+    pub fn sysname(&self) -> io::Result<String> {
+        let mut buf = [0 as c_char; 64];
+        check(unsafe { ui_get_sysname(self.as_raw_fd(), &mut buf) })?;
+        Ok(c_string(&buf))
+    }
+
+    /// Emit an event.  Consumers only see events after a `SYN_REPORT`.
+    pub fn write_event(&self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        write_event(&self.file, type_, code, value)
+    }
+}
+
+impl AsRawFd for VirtualDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+}
+
+impl Drop for VirtualDevice {
+    fn drop(&mut self) {
+        let _ = unsafe { ui_dev_destroy(self.file.as_raw_fd()) };
+    }
+}